@@ -0,0 +1,328 @@
+use std::fmt::Display;
+
+use crate::{
+    game::{Game, Glyphs, Hint, Outcome, Refined, Segment, Square},
+    parse::ParseError,
+    topology::{Line, Topology},
+};
+
+/// Which way a line through a cube runs
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+impl Axis {
+    pub const ALL: [Axis; 3] = [Axis::X, Axis::Y, Axis::Z];
+}
+
+/// How a Picross 3D clue says the filled cells in its line are split up
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Grouping {
+    /// Plain number, all in one run
+    Plain,
+    /// Circled number, exactly 2 runs
+    Circled,
+    /// Squared number, 3 or more runs
+    Squared,
+}
+
+/// Picross 3D clue: how many cells in a line are filled and how many runs they make
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CubeClue {
+    pub count: u32,
+    pub grouping: Grouping,
+}
+
+impl CubeClue {
+    /// Every plain hint the clue could mean in a line of len, so the usual line logic can go
+    /// through them one at a time
+    pub fn hints(&self, len: usize) -> Vec<Hint> {
+        /// Every way to split count into runs lengths of at least 1, in order
+        fn split(count: usize, runs: usize, lens: &mut Vec<u32>, hints: &mut Vec<Hint>) {
+            if runs == 0 {
+                if count == 0 {
+                    hints.push(lens.iter().map(|len| Segment::from(*len)).collect());
+                }
+                return;
+            }
+            // Leave at least 1 for each run still to come
+            for len in 1..=count.saturating_sub(runs - 1) {
+                lens.push(len as u32);
+                split(count - len, runs - 1, lens, hints);
+                lens.pop();
+            }
+        }
+
+        let count = self.count as usize;
+        let runs = match (self.grouping, count) {
+            (_, 0) => 0..=0,
+            (Grouping::Plain, _) => 1..=1,
+            (Grouping::Circled, _) => 2..=2,
+            // Every run after the first needs a gap in front of it
+            (Grouping::Squared, _) => 3..=(len + 1).saturating_sub(count),
+        };
+        let mut hints = vec![];
+        for runs in runs {
+            split(count, runs, &mut vec![], &mut hints);
+        }
+        hints
+    }
+}
+
+impl Display for CubeClue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.grouping {
+            Grouping::Plain => write!(f, "{}", self.count),
+            Grouping::Circled => write!(f, "({})", self.count),
+            Grouping::Squared => write!(f, "[{}]", self.count),
+        }
+    }
+}
+
+/// Picross 3D puzzle. A block of cells size[0] wide (x), size[1] high (y) and size[2] deep (z),
+/// with a clue for each line through it along each axis. Cells are Squares, only ever black.
+/// Lines along x are numbered by y then z, lines along y by x then z, and along z by x then y
+#[derive(Clone, Debug)]
+pub struct Cube {
+    pub size: [usize; 3],
+    /// Every cell, x fastest then y then z. See Cube::index
+    pub cells: Vec<Square>,
+    /// Clue for each line along each axis, indexed by Axis then line. None where the clue's
+    /// been left out
+    pub clues: [Vec<Option<CubeClue>>; 3],
+}
+
+impl Cube {
+    pub fn index(&self, x: usize, y: usize, z: usize) -> usize {
+        let [w, h, _] = self.size;
+        x + w * (y + h * z)
+    }
+
+    /// How many lines run along axis
+    pub fn lines(&self, axis: Axis) -> usize {
+        let [w, h, d] = self.size;
+        match axis {
+            Axis::X => h * d,
+            Axis::Y => w * d,
+            Axis::Z => w * h,
+        }
+    }
+
+    /// Indices into cells of line i along axis, in order along it
+    pub fn line_cells(&self, axis: Axis, i: usize) -> Vec<usize> {
+        let [w, h, d] = self.size;
+        match axis {
+            Axis::X => (0..w).map(|x| self.index(x, i % h, i / h)).collect(),
+            Axis::Y => (0..h).map(|y| self.index(i % w, y, i / w)).collect(),
+            Axis::Z => (0..d).map(|z| self.index(i % w, i / w, z)).collect(),
+        }
+    }
+
+    /// Every line through the cube, along x then y then z
+    pub fn topology(&self) -> Topology<Option<CubeClue>> {
+        let lines = Axis::ALL
+            .iter()
+            .flat_map(|axis| {
+                (0..self.lines(*axis))
+                    .map(|i| Line::new(self.line_cells(*axis, i), self.clues[*axis as usize][i]))
+            })
+            .collect();
+        Topology { lines }
+    }
+
+    /// Line logic for a line of a cube: whatever every hint the clue could mean agrees on, each
+    /// worked out with refine_line_complete. Gives back the same as the Game line solvers
+    pub fn refine_line(line: &[Square], clue: Option<&CubeClue>) -> Refined {
+        // Nothing to go on without a clue, the other axes have to do it all
+        let Some(clue) = clue else {
+            return Some((line.to_vec(), !line.contains(&Square::Unknown), false));
+        };
+        let new_line = clue
+            .hints(line.len())
+            .iter()
+            .filter_map(|hint| Game::refine_line_complete(line, hint))
+            .fold(None, |agreed, (way, _, _)| Some(Game::agree(agreed, way)))?;
+        let solved = !new_line.contains(&Square::Unknown);
+        let changed = new_line != line;
+        Some((new_line, solved, changed))
+    }
+
+    /// Runs line logic along every axis until the cube's done, it gets stuck, or some line can't
+    /// fit its clue
+    pub fn solve(&mut self) -> Outcome {
+        self.topology().propagate(&mut self.cells, |line, clue| {
+            Cube::refine_line(line, clue.as_ref())
+        })
+    }
+
+    /// The cube as plain text, one z slice after another. See slices
+    pub fn to_text(&self, glyphs: &Glyphs) -> String {
+        self.slices(|square| glyphs.get(square).to_string())
+    }
+
+    /// Same as to_text, with the squares styled for the terminal
+    pub fn render_slices(&self) -> String {
+        self.slices(|square| square.to_string())
+    }
+
+    /// Draws each z slice like a 2D puzzle, with the clues for the lines along x that run
+    /// through it down the left and the lines along y along the top. Lines along z go through
+    /// every slice, so their clues come last on their own, laid out the way the slices are.
+    /// EX:
+    /// slice 1
+    ///       2  (2)
+    ///     1 .   .
+    ///   (2) .   .
+    fn slices(&self, cell: impl Fn(&Square) -> String) -> String {
+        let [w, h, d] = self.size;
+        let label = |clue: &Option<CubeClue>| clue.map_or(String::new(), |clue| clue.to_string());
+        let [x_labels, y_labels, z_labels] = self
+            .clues
+            .clone()
+            .map(|clues| clues.iter().map(label).collect::<Vec<_>>());
+        let width = y_labels
+            .iter()
+            .chain(&z_labels)
+            .map(|label| label.chars().count())
+            .max()
+            .unwrap_or(1)
+            .max(1);
+        let left = x_labels
+            .iter()
+            .map(|label| label.chars().count())
+            .max()
+            .unwrap_or(0);
+        let row_of_labels = |labels: &[String]| -> String {
+            let labels: Vec<String> = labels
+                .iter()
+                .map(|label| format!("{:>width$}", label))
+                .collect();
+            format!("{:left$} {}", "", labels.join(" "))
+        };
+
+        let mut lines = vec![];
+        for z in 0..d {
+            lines.push(format!("slice {}", z + 1));
+            lines.push(row_of_labels(&y_labels[w * z..w * (z + 1)]));
+            for y in 0..h {
+                // Squares can come styled, so pad them by hand instead of with format widths
+                let cells: Vec<String> = (0..w)
+                    .map(|x| " ".repeat(width - 1) + &cell(&self.cells[self.index(x, y, z)]))
+                    .collect();
+                lines.push(format!(
+                    "{:>left$} {}",
+                    x_labels[y + h * z],
+                    cells.join(" ")
+                ));
+            }
+            lines.push(String::new());
+        }
+        lines.push("through the slices".to_string());
+        for y in 0..h {
+            lines.push(row_of_labels(&z_labels[w * y..w * (y + 1)]));
+        }
+        lines
+            .iter()
+            .map(|line| line.trim_end())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Cube puzzle files start with cube and the size, then the clues for each axis under its
+/// name. Clues are written in the order their lines are numbered, a row per slice (per row of
+/// the front for z), with * for a left out clue. A plain number is one run, (n) is n in exactly
+/// 2 runs and [n] is n in 3 or more. Blank lines don't matter.
+/// EX:
+/// cube 2 2 1
+/// x
+/// 2 (2)
+/// y
+/// 1 *
+/// z
+/// 1 1
+/// 0 1
+impl Cube {
+    pub fn from_clues_str(s: &str) -> Result<Cube, ParseError> {
+        let mut lines = s
+            .lines()
+            .map(|line| line.trim())
+            .enumerate()
+            .filter(|(_, line)| !line.is_empty())
+            .map(|(i, line)| (i + 1, line));
+        let bad_cube = |line, problem| ParseError::BadLayout { line, problem };
+
+        let (header_line, header) = lines.next().ok_or(ParseError::Empty)?;
+        let size: Vec<usize> = header
+            .strip_prefix("cube")
+            .ok_or(bad_cube(header_line, "should start with cube and its size"))?
+            .split_whitespace()
+            .map(|n| n.parse())
+            .collect::<Result<_, _>>()
+            .map_err(|_| bad_cube(header_line, "size should be 3 numbers"))?;
+        let size: [usize; 3] = size
+            .try_into()
+            .map_err(|_| bad_cube(header_line, "size should be 3 numbers"))?;
+        if size.contains(&0) {
+            return Err(ParseError::Empty);
+        }
+        let mut cube = Cube {
+            size,
+            cells: vec![Square::Unknown; size.iter().product()],
+            clues: [vec![], vec![], vec![]],
+        };
+
+        let mut axis = None;
+        let mut last_line = header_line;
+        for (line_num, line) in lines {
+            last_line = line_num;
+            match line {
+                "x" => axis = Some(Axis::X),
+                "y" => axis = Some(Axis::Y),
+                "z" => axis = Some(Axis::Z),
+                _ => {
+                    let axis = axis.ok_or(bad_cube(line_num, "clues need an axis first"))?;
+                    let len = cube.size[axis as usize];
+                    for clue in line.split_whitespace() {
+                        let clue = parse_clue(clue, len).ok_or(ParseError::BadSegment {
+                            line: line_num,
+                            segment: clue.to_string(),
+                        })?;
+                        cube.clues[axis as usize].push(clue);
+                    }
+                }
+            }
+        }
+        if Axis::ALL
+            .iter()
+            .any(|axis| cube.clues[*axis as usize].len() != cube.lines(*axis))
+        {
+            return Err(bad_cube(last_line, "wrong number of clues for the size"));
+        }
+        Ok(cube)
+    }
+}
+
+/// Reads a single clue, checking it can fit a line of len. Some(None) for *
+fn parse_clue(clue: &str, len: usize) -> Option<Option<CubeClue>> {
+    if clue == "*" {
+        return Some(None);
+    }
+    let (count, grouping) = match (clue.strip_prefix('('), clue.strip_prefix('[')) {
+        (Some(rest), _) => (rest.strip_suffix(')')?, Grouping::Circled),
+        (_, Some(rest)) => (rest.strip_suffix(']')?, Grouping::Squared),
+        _ => (clue, Grouping::Plain),
+    };
+    let count: u32 = count.parse().ok()?;
+    // Fewest runs it can mean, each after the first needs a gap
+    let runs = match (grouping, count) {
+        (Grouping::Plain, _) => 0,
+        (Grouping::Circled, 2..) => 1,
+        (Grouping::Squared, 3..) => 2,
+        _ => return None,
+    };
+    (count as usize + runs <= len).then_some(Some(CubeClue { count, grouping }))
+}
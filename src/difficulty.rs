@@ -0,0 +1,158 @@
+use std::fmt::Display;
+
+use crate::{
+    game::{Game, LineLogic, Outcome, SolveStats, Solver, Square},
+    line_solver::Cached,
+};
+
+/// Rough buckets for how hard a puzzle is, by the hardest trick it needs
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Category {
+    /// Line logic, and not many sweeps of it
+    Easy,
+    /// Line logic, but it takes a while
+    Medium,
+    /// Needs probing: trying a square both ways to see which one breaks
+    Hard,
+    /// Needs actual guessing and backtracking
+    Expert,
+}
+
+impl Display for Category {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Category::Easy => "easy",
+            Category::Medium => "medium",
+            Category::Hard => "hard",
+            Category::Expert => "expert",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The least a solver has to know to finish a puzzle
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Tier {
+    /// Left-most/right-most overlap on single lines is enough
+    Overlap,
+    /// Needs everything single lines can give, not just the overlap
+    LineSolver,
+    /// Needs probing on top of the complete line solver
+    Probing,
+    /// Needs guessing
+    Backtracking,
+}
+
+impl Display for Tier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Tier::Overlap => "overlap",
+            Tier::LineSolver => "line",
+            Tier::Probing => "probing",
+            Tier::Backtracking => "backtracking",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl Tier {
+    /// Reads the names Display writes
+    pub fn from_name(name: &str) -> Option<Tier> {
+        [
+            Tier::Overlap,
+            Tier::LineSolver,
+            Tier::Probing,
+            Tier::Backtracking,
+        ]
+        .into_iter()
+        .find(|tier| tier.to_string() == name)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Difficulty {
+    /// Higher is harder. Only meant for comparing puzzles against each other
+    pub score: usize,
+    pub category: Category,
+    pub stats: SolveStats,
+}
+
+/// Line-only puzzles solved in this many sweeps or fewer count as easy
+const EASY_PASSES: usize = 4;
+
+/// Score each category starts at
+const CATEGORY_SCORE: usize = 1_000_000;
+
+impl Difficulty {
+    pub fn from_stats(stats: SolveStats) -> Self {
+        let category = match stats {
+            SolveStats { guesses: 1.., .. } => Category::Expert,
+            SolveStats { probes: 1.., .. } => Category::Hard,
+            SolveStats { passes, .. } if passes <= EASY_PASSES => Category::Easy,
+            _ => Category::Medium,
+        };
+        // The category always decides the order, however big the puzzle, and the rest only sorts
+        // puzzles within it
+        let effort = stats.passes * 10
+            + stats.lines_refined
+            + stats.probes * 20
+            + stats.guesses * 50
+            + stats.max_depth * 100;
+        let score = category as usize * CATEGORY_SCORE + effort.min(CATEGORY_SCORE - 1);
+        Difficulty {
+            score,
+            category,
+            stats,
+        }
+    }
+}
+
+impl Game {
+    /// How hard the puzzle is to solve from scratch. Tries line logic first, then probing, then
+    /// backtracking, and rates by what it took. None if it doesn't have exactly one solution
+    pub fn rate(&self) -> Option<Difficulty> {
+        let mut blank = self.clone();
+        blank.grid = vec![vec![Square::Unknown; self.cols]; self.rows];
+        let mut solver = Solver::new(blank);
+        match solver.probe() {
+            Outcome::Solved => {}
+            Outcome::Contradiction => return None,
+            // Guess from wherever probing got to, which keeps the guesses down to the ones that
+            // were really needed
+            Outcome::Stuck => {
+                if solver.search(2).len() != 1 {
+                    return None;
+                }
+            }
+        }
+        Some(Difficulty::from_stats(solver.stats))
+    }
+
+    /// Which tier the puzzle falls in, trying each technique from a blank grid in order. None if
+    /// it doesn't have exactly one solution
+    pub fn tier(&self) -> Option<Tier> {
+        let mut blank = self.clone();
+        blank.grid = vec![vec![Square::Unknown; self.cols]; self.rows];
+
+        let mut solver = Solver::new(blank);
+        match solver.solve(&mut None) {
+            Outcome::Solved => return Some(Tier::Overlap),
+            Outcome::Contradiction => return None,
+            Outcome::Stuck => {}
+        }
+        // Everything overlap found still holds, so carry on from there. Its cached lines don't
+        // though
+        solver.line_logic = Cached::new(LineLogic::Complete);
+        match solver.solve(&mut None) {
+            Outcome::Solved => return Some(Tier::LineSolver),
+            Outcome::Contradiction => return None,
+            Outcome::Stuck => {}
+        }
+        match solver.probe() {
+            Outcome::Solved => return Some(Tier::Probing),
+            Outcome::Contradiction => return None,
+            Outcome::Stuck => {}
+        }
+        (solver.search(2).len() == 1).then_some(Tier::Backtracking)
+    }
+}
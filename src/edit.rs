@@ -0,0 +1,344 @@
+use std::{
+    fs,
+    io::{self, Write},
+};
+
+use anyhow::{bail, Result};
+use crossterm::{
+    cursor,
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind,
+        KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+    },
+    execute, queue,
+    style::Print,
+    terminal::{self, ClearType},
+};
+
+use crate::{
+    game::{Color, Corner, Game, Grid, Solver, Square},
+    generate::ambiguous_cells,
+    render::Highlight,
+};
+
+const HELP: &str = "arrows/hjkl: move  space: fill  x: erase  shift+move: paint along  \
+                    g: grid lines  w: wrap round  1-9: color  t: triangle  u: check unique  s: save  q: quit";
+
+/// Editor mode for drawing puzzles. game.grid is the picture being drawn, filled or Unknown, and
+/// the hints get worked out from it after every change
+pub struct Edit {
+    pub game: Game,
+    /// (row, col) of the cursor
+    pub cursor: (usize, usize),
+    /// Where s saves to. The extension picks the format
+    pub path: String,
+    /// What dragging paints with, set by whatever the last fill/erase was
+    brush: Option<Square>,
+    /// Palette color filling paints with
+    pub color: Color,
+    /// Cells the current hints don't pin down. What line logic leaves unknown, or once searched
+    /// is set, where 2 solutions disagree
+    pub ambiguous: Vec<(usize, usize)>,
+    /// Whether line logic solves the current hints without any guessing
+    pub line_solvable: bool,
+    /// Whether ambiguous came from searching for a 2nd solution. That can take seconds on big
+    /// pictures, so it only happens when asked for with u rather than after every change
+    pub searched: bool,
+    pub message: Vec<String>,
+}
+
+impl Edit {
+    /// Starts from the game's solution if it has one, otherwise from whatever its grid has filled
+    pub fn new(mut game: Game, path: String) -> Self {
+        let picture = game.solution.take().unwrap_or(game.grid.clone());
+        // Hints get read straight off the picture, which only ever gives plain ones
+        game.mega_rows.clear();
+        game.mega_cols.clear();
+        game.grid = picture
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .map(|square| match square.is_filled() {
+                        true => square,
+                        false => Square::Unknown,
+                    })
+                    .collect()
+            })
+            .collect();
+        let mut edit = Edit {
+            game,
+            cursor: (0, 0),
+            path,
+            brush: None,
+            color: 0,
+            ambiguous: vec![],
+            line_solvable: false,
+            searched: false,
+            message: vec![],
+        };
+        edit.refresh();
+        edit
+    }
+
+    /// Opens a puzzle file's game for editing. The picture is its solution if it has one, or else
+    /// any picture that fits the clues. Errors if nothing does, so there's no picture to save over
+    /// the file with
+    pub fn open(mut game: Game, path: String) -> Result<Self> {
+        let mut message = vec![];
+        if !game.find_solution() {
+            let mut blank = game.clone();
+            blank.grid = vec![vec![Square::Unknown; game.cols]; game.rows];
+            let Some(picture) = Solver::new(blank).search(1).pop() else {
+                bail!("the clues have no solution, so there's no picture to edit");
+            };
+            game.solution = Some(picture);
+            message.push("The clues fit more than one picture, this is one of them".to_string());
+        }
+        if !game.mega_rows.is_empty() || !game.mega_cols.is_empty() {
+            message.push("Mega clues become plain ones, saving will replace them".to_string());
+        }
+        let mut edit = Edit::new(game, path);
+        edit.message = message;
+        Ok(edit)
+    }
+
+    /// Blank canvas
+    pub fn blank(rows: usize, cols: usize, path: String) -> Self {
+        let game = Game::from_solution(vec![vec![Square::Empty; cols]; rows]);
+        Edit::new(game, path)
+    }
+
+    /// The picture as a puzzle, with the picture as its solution
+    pub fn puzzle(&self) -> Game {
+        let picture: Grid = self
+            .game
+            .grid
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|square| match square.is_filled() {
+                        true => square.clone(),
+                        false => Square::Empty,
+                    })
+                    .collect()
+            })
+            .collect();
+        let mut puzzle = match self.game.wrap {
+            true => Game::wrapped_from_solution(picture),
+            false => Game::from_solution(picture),
+        };
+        puzzle.separator_every = self.game.separator_every;
+        puzzle.palette = self.game.palette.clone();
+        puzzle
+    }
+
+    /// Rederives the hints and what's ambiguous about them after the picture changed
+    fn refresh(&mut self) {
+        let puzzle = self.puzzle();
+        self.ambiguous = ambiguous_cells(&puzzle, false);
+        self.line_solvable = self.ambiguous.is_empty();
+        self.searched = false;
+        self.game.row_hints = puzzle.row_hints;
+        self.game.col_hints = puzzle.col_hints;
+    }
+
+    pub fn run(&mut self) -> Result<()> {
+        let mut stdout = io::stdout();
+        terminal::enable_raw_mode()?;
+        execute!(stdout, terminal::EnterAlternateScreen, EnableMouseCapture)?;
+
+        let result = self.event_loop(&mut stdout);
+
+        // Always try to give the terminal back, even if the loop blew up
+        execute!(stdout, DisableMouseCapture, terminal::LeaveAlternateScreen)?;
+        terminal::disable_raw_mode()?;
+        result
+    }
+
+    fn event_loop(&mut self, stdout: &mut io::Stdout) -> Result<()> {
+        loop {
+            self.draw(stdout)?;
+            match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press && !self.handle_key(key) => {
+                    return Ok(());
+                }
+                Event::Mouse(mouse) => self.handle_mouse(mouse),
+                _ => {}
+            }
+        }
+    }
+
+    /// Returns false when the author wants out
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        let (row, col) = self.cursor;
+        let (last_row, last_col) = (self.game.rows - 1, self.game.cols - 1);
+        let shift = key.modifiers.contains(KeyModifiers::SHIFT);
+        let paint_to = match key.code {
+            KeyCode::Up if shift => Some((row.saturating_sub(1), col)),
+            KeyCode::Down if shift => Some(((row + 1).min(last_row), col)),
+            KeyCode::Left if shift => Some((row, col.saturating_sub(1))),
+            KeyCode::Right if shift => Some((row, (col + 1).min(last_col))),
+            KeyCode::Char('K') => Some((row.saturating_sub(1), col)),
+            KeyCode::Char('J') => Some(((row + 1).min(last_row), col)),
+            KeyCode::Char('H') => Some((row, col.saturating_sub(1))),
+            KeyCode::Char('L') => Some((row, (col + 1).min(last_col))),
+            _ => None,
+        };
+        if let Some(cell) = paint_to {
+            self.cursor = cell;
+            if let Some(brush) = self.brush.clone() {
+                self.paint(brush);
+            }
+            return true;
+        }
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Up | KeyCode::Char('k') => self.cursor.0 = row.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => self.cursor.0 = (row + 1).min(last_row),
+            KeyCode::Left | KeyCode::Char('h') => self.cursor.1 = col.saturating_sub(1),
+            KeyCode::Right | KeyCode::Char('l') => self.cursor.1 = (col + 1).min(last_col),
+            KeyCode::Char(' ') | KeyCode::Char('f') => {
+                let brush = match self.game.grid[row][col] {
+                    Square::Filled(color) if color == self.color => Square::Unknown,
+                    _ => Square::Filled(self.color),
+                };
+                self.paint(brush)
+            }
+            KeyCode::Char('x') => self.paint(Square::Unknown),
+            KeyCode::Char('t') => {
+                let brush = match &self.game.grid[row][col] {
+                    Square::Triangle(corner) => match corner.clockwise() {
+                        Some(next) => Square::Triangle(next),
+                        None => Square::Unknown,
+                    },
+                    _ => Square::Triangle(Corner::TopLeft),
+                };
+                self.paint(brush)
+            }
+            KeyCode::Char(digit @ '1'..='9') => {
+                let color = digit as usize - '1' as usize;
+                if color < self.game.palette.len() {
+                    self.color = color as Color;
+                    self.message = vec![format!("Color {}", digit)];
+                }
+            }
+            KeyCode::Char('w') => {
+                self.game.wrap = !self.game.wrap;
+                self.refresh();
+                self.message = vec![format!(
+                    "Wrapping {}",
+                    if self.game.wrap { "on" } else { "off" }
+                )];
+            }
+            KeyCode::Char('g') => {
+                self.game.separator_every = match self.game.separator_every {
+                    Some(_) => None,
+                    None => Some(5),
+                }
+            }
+            KeyCode::Char('u') if !self.line_solvable => {
+                self.ambiguous = ambiguous_cells(&self.puzzle(), true);
+                self.searched = true;
+            }
+            KeyCode::Char('s') => {
+                self.message = match self.save() {
+                    Ok(()) => vec![format!("Saved to {}", self.path)],
+                    Err(err) => vec![format!("Couldn't save: {}", err)],
+                }
+            }
+            _ => {}
+        }
+        true
+    }
+
+    pub fn handle_mouse(&mut self, mouse: MouseEvent) {
+        let cell = self.game.cell_at(mouse.column as usize, mouse.row as usize);
+        match (mouse.kind, cell) {
+            (MouseEventKind::Down(button), Some(cell)) => {
+                self.cursor = cell;
+                match button {
+                    MouseButton::Right => self.paint(Square::Unknown),
+                    _ => self.paint(Square::Filled(self.color)),
+                }
+            }
+            (MouseEventKind::Drag(_), Some(cell)) => {
+                self.cursor = cell;
+                if let Some(brush) = self.brush.clone() {
+                    self.paint(brush);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Sets the square under the cursor and makes it the brush for dragging
+    fn paint(&mut self, square: Square) {
+        let (row, col) = self.cursor;
+        self.brush = Some(square.clone());
+        self.message.clear();
+        if self.game.grid[row][col] != square {
+            self.game.grid[row][col] = square;
+            self.refresh();
+        }
+    }
+
+    /// Writes the puzzle to path. .svg gets a blank printable puzzle, .pbm/.pgm/.png get the
+    /// picture itself, and anything else gets the text format with the picture as its solution
+    pub fn save(&self) -> Result<()> {
+        let mut puzzle = self.puzzle();
+        let bytes = match self.path.rsplit_once('.').map(|(_, ext)| ext) {
+            Some("svg") => puzzle.to_svg(false).into_bytes(),
+            Some(ext @ ("pbm" | "pgm" | "png")) => {
+                puzzle.grid = puzzle.solution.clone().expect("from_solution sets it");
+                match ext {
+                    "pbm" => puzzle.to_pbm(1),
+                    "pgm" => puzzle.to_pgm(1),
+                    _ => puzzle.to_png(1)?,
+                }
+            }
+            _ => puzzle.hints_to_string().into_bytes(),
+        };
+        fs::write(&self.path, bytes)?;
+        Ok(())
+    }
+
+    fn draw(&mut self, stdout: &mut io::Stdout) -> Result<()> {
+        let highlight = Highlight {
+            ambiguous: self.ambiguous.clone(),
+            cursor: Some(self.cursor),
+            ..Default::default()
+        };
+        let rendered = self.game.render_all(&highlight);
+        queue!(stdout, terminal::Clear(ClearType::All))?;
+        for (y, line) in rendered.iter().enumerate() {
+            queue!(stdout, cursor::MoveTo(0, y as u16), Print(line.concat()))?;
+        }
+        let uniqueness = match (self.ambiguous.len(), self.line_solvable, self.searched) {
+            (_, true, _) => "Unique, line logic solves it".to_string(),
+            (0, false, _) => "Unique, but line logic needs guessing to solve it".to_string(),
+            (n, false, true) => format!("{} square(s) the clues don't pin down (magenta)", n),
+            (n, false, false) => format!(
+                "{} square(s) line logic can't work out (magenta), u to check if it's unique",
+                n
+            ),
+        };
+        let counters = format!(
+            "Row {} Col {}  {}",
+            self.cursor.0 + 1,
+            self.cursor.1 + 1,
+            uniqueness
+        );
+        let status = std::iter::once(counters.as_str())
+            .chain(self.message.iter().map(|line| line.as_str()))
+            .chain([HELP]);
+        for (y, line) in (rendered.len() + 1..).zip(status) {
+            queue!(stdout, cursor::MoveTo(0, y as u16), Print(line))?;
+        }
+        // Park the terminal cursor on the grid cell we're on
+        let (x, y) = self.game.screen_pos(self.cursor.0, self.cursor.1);
+        queue!(stdout, cursor::MoveTo(x as u16, y as u16), cursor::Show)?;
+        stdout.flush()?;
+        Ok(())
+    }
+}
@@ -1,9 +1,175 @@
-use core::panic;
-use std::{collections::BinaryHeap, fmt::Display, fs::File, io::Write, iter::zip, usize};
+use std::{collections::HashSet, fmt::Display, fs::File, io::Write, iter::zip};
 
-use anyhow::Result;
+use anyhow::{bail, Result};
+
+use crate::{
+    line_solver::{Cached, LineSolver, Overlap},
+    topology::{Line, Topology},
+};
+
+/// Index into Game::palette. Black and white puzzles only ever use 0, which is black
+pub type Color = u8;
+
+/// One run of filled squares in a hint. Runs next to each other need a gap between them only when
+/// they'd join up otherwise, which is when they're the same color and the squares that meet both
+/// cover the side they meet on
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Segment {
+    pub len: u32,
+    pub color: Color,
+    /// Triangle the run starts with, None for a whole square. For a run of 1 it's the same as end
+    pub start: Option<Corner>,
+    /// Triangle the run ends with, None for a whole square
+    pub end: Option<Corner>,
+    /// The puzzle doesn't say how long it is, written ?. len is the shortest it could be, 1.
+    /// Mystery puzzles have these
+    pub hidden: bool,
+}
+
+impl Segment {
+    pub fn new(len: u32, color: Color) -> Self {
+        Segment {
+            len,
+            color,
+            start: None,
+            end: None,
+            hidden: false,
+        }
+    }
+
+    /// Segment of any length, written ?
+    pub fn hidden(color: Color) -> Self {
+        Segment {
+            hidden: true,
+            ..Segment::new(1, color)
+        }
+    }
+
+    /// Hint for a line whose clue is left out altogether, written *. Could be any number of
+    /// segments of any length, so the line can only be worked out from the other direction
+    pub fn missing_clue() -> Hint {
+        vec![Segment {
+            hidden: true,
+            ..Segment::new(0, 0)
+        }]
+    }
+
+    /// Whether a hint is missing_clue
+    pub fn is_missing(hint: &[Segment]) -> bool {
+        hint == Segment::missing_clue()
+    }
+
+    /// Fewest squares a hint fits in, counting the gaps it needs. Hidden lengths count as 1 and
+    /// a missing clue fits anywhere
+    pub fn min_len(hint: &[Segment]) -> usize {
+        if Segment::is_missing(hint) {
+            return 0;
+        }
+        (0..hint.len())
+            .map(|seg| hint[seg].len as usize + needs_gap(hint, seg) as usize)
+            .sum()
+    }
+
+    /// Whether a run read off a line could be this segment
+    pub fn allows(&self, found: &Segment) -> bool {
+        match self.hidden {
+            true => self.color == found.color && found.start.is_none() && found.end.is_none(),
+            false => self == found,
+        }
+    }
+
+    /// What the square offset squares into the run has to be, reading the run left to right
+    pub fn square_at(&self, offset: usize) -> Square {
+        let corner = match offset {
+            0 => self.start,
+            _ if offset + 1 == self.len as usize => self.end,
+            _ => None,
+        };
+        match corner {
+            Some(corner) => Square::Triangle(corner),
+            None => Square::Filled(self.color),
+        }
+    }
+
+    /// Whether the run can actually be laid out along a row: the squares in it have to join up
+    /// with each other, so a longer run can only start with a triangle covering its right side
+    /// and end with one covering its left
+    pub fn is_valid(&self) -> bool {
+        let whole = self.start.is_none() && self.end.is_none();
+        // Hidden lengths don't say where any triangles would go, so they can't have any
+        if self.hidden {
+            return whole;
+        }
+        let black = self.color == 0 || whole;
+        let joins = match self.len {
+            0 => true,
+            1 => self.start == self.end,
+            _ => {
+                self.start.is_none_or(|corner| corner.right())
+                    && self.end.is_none_or(|corner| corner.left())
+            }
+        };
+        black && joins
+    }
+
+    /// Same run read along a column as if it were a row. See Corner::transposed
+    pub fn transposed(&self) -> Segment {
+        Segment {
+            start: self.start.map(Corner::transposed),
+            end: self.end.map(Corner::transposed),
+            ..*self
+        }
+    }
+
+    /// Same run read right to left
+    pub fn reversed(&self) -> Segment {
+        Segment {
+            start: self.end.map(Corner::mirrored),
+            end: self.start.map(Corner::mirrored),
+            ..*self
+        }
+    }
+}
+
+impl From<u32> for Segment {
+    /// Plain black segment
+    fn from(len: u32) -> Self {
+        Segment::new(len, 0)
+    }
+}
+
+impl Display for Segment {
+    /// The length, with any triangle ends either side of it. A lone triangle just goes in front.
+    /// Hidden lengths are ?, and a missing clue is *.
+    /// EX: 3, ◢3◣ or ◥1
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.hidden, self.len) {
+            (true, 0) => return write!(f, "*"),
+            (true, _) => return write!(f, "?"),
+            _ => {}
+        }
+        if let Some(start) = self.start {
+            write!(f, "{}", start.glyph())?;
+        }
+        write!(f, "{}", self.len)?;
+        match self.end {
+            Some(end) if self.len > 1 => write!(f, "{}", end.glyph()),
+            _ => Ok(()),
+        }
+    }
+}
+
+pub type Hint = Vec<Segment>;
+
+/// Whether a gap has to go after the segment at seg, which is whenever the next one would join up
+/// with it
+fn needs_gap(hint: &[Segment], seg: usize) -> bool {
+    hint.get(seg + 1).is_some_and(|next| {
+        let last = hint[seg].square_at(hint[seg].len as usize - 1);
+        next.color == hint[seg].color && last.joins_right() && next.square_at(0).joins_left()
+    })
+}
 
-pub type Hint = Vec<u32>;
 /// Given a hint and a line with some of the segments placed, a line of SegmentPlacements may look
 /// like this:
 /// Given:
@@ -15,38 +181,348 @@ pub type Hint = Vec<u32>;
 /// the Hints that is at that possition
 pub type SegmentPlacement = Option<usize>;
 
-#[derive(Clone, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Square {
     Unknown,
-    Filled,
+    Filled(Color),
+    /// Black half of a square, split along the diagonal. For triangle (Triddler style) puzzles
+    Triangle(Corner),
     Empty,
 }
 
+impl Square {
+    /// Anything with some fill to it, whole square or triangle
+    pub fn is_filled(&self) -> bool {
+        matches!(self, Square::Filled(_) | Square::Triangle(_))
+    }
+
+    /// Color of whatever's filled in. Triangles are always black
+    pub fn color(&self) -> Option<Color> {
+        match self {
+            Square::Filled(color) => Some(*color),
+            Square::Triangle(_) => Some(0),
+            _ => None,
+        }
+    }
+
+    /// Whether it joins up with a filled square to its left in a row
+    pub fn joins_left(&self) -> bool {
+        match self {
+            Square::Filled(_) => true,
+            Square::Triangle(corner) => corner.left(),
+            _ => false,
+        }
+    }
+
+    /// Whether it joins up with a filled square to its right in a row
+    pub fn joins_right(&self) -> bool {
+        match self {
+            Square::Filled(_) => true,
+            Square::Triangle(corner) => corner.right(),
+            _ => false,
+        }
+    }
+
+    /// See Corner::transposed
+    pub fn transposed(&self) -> Square {
+        match self {
+            Square::Triangle(corner) => Square::Triangle(corner.transposed()),
+            square => square.clone(),
+        }
+    }
+
+    /// See Corner::mirrored
+    pub fn mirrored(&self) -> Square {
+        match self {
+            Square::Triangle(corner) => Square::Triangle(corner.mirrored()),
+            square => square.clone(),
+        }
+    }
+}
+
+/// Corner of the square a triangle fills. The triangle covers the 2 sides that meet at it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl Corner {
+    pub const ALL: [Corner; 4] = [
+        Corner::TopLeft,
+        Corner::TopRight,
+        Corner::BottomLeft,
+        Corner::BottomRight,
+    ];
+
+    /// Covers the left side
+    pub fn left(self) -> bool {
+        matches!(self, Corner::TopLeft | Corner::BottomLeft)
+    }
+
+    /// Covers the right side
+    pub fn right(self) -> bool {
+        matches!(self, Corner::TopRight | Corner::BottomRight)
+    }
+
+    /// Flipped over the top-left to bottom-right diagonal, so top turns into left and bottom into
+    /// right. Columns get transposed on the way into the line solvers so they only ever have to
+    /// think about left and right
+    pub fn transposed(self) -> Corner {
+        match self {
+            Corner::TopRight => Corner::BottomLeft,
+            Corner::BottomLeft => Corner::TopRight,
+            corner => corner,
+        }
+    }
+
+    /// Next corner round going clockwise, None after going all the way round from top left
+    pub fn clockwise(self) -> Option<Corner> {
+        match self {
+            Corner::TopLeft => Some(Corner::TopRight),
+            Corner::TopRight => Some(Corner::BottomRight),
+            Corner::BottomRight => Some(Corner::BottomLeft),
+            Corner::BottomLeft => None,
+        }
+    }
+
+    /// Flipped left to right
+    pub fn mirrored(self) -> Corner {
+        match self {
+            Corner::TopLeft => Corner::TopRight,
+            Corner::TopRight => Corner::TopLeft,
+            Corner::BottomLeft => Corner::BottomRight,
+            Corner::BottomRight => Corner::BottomLeft,
+        }
+    }
+
+    /// How the triangle gets drawn, and written in puzzle files
+    pub fn glyph(self) -> char {
+        match self {
+            Corner::TopLeft => '◤',
+            Corner::TopRight => '◥',
+            Corner::BottomLeft => '◣',
+            Corner::BottomRight => '◢',
+        }
+    }
+
+    pub fn from_glyph(glyph: char) -> Option<Corner> {
+        Corner::ALL
+            .into_iter()
+            .find(|corner| corner.glyph() == glyph)
+    }
+}
+
+/// A color puzzles can fill squares with
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PaletteColor {
+    /// Used for the color in puzzle files and plain text output
+    pub symbol: char,
+    pub rgb: (u8, u8, u8),
+}
+
+impl PaletteColor {
+    pub fn black() -> Self {
+        PaletteColor {
+            symbol: 'o',
+            rgb: (0, 0, 0),
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct Game {
     pub rows: usize,
     pub cols: usize,
     pub col_hints: Vec<Hint>,
     pub row_hints: Vec<Hint>,
     pub grid_pos: Option<(usize, usize)>, // Calculated when render_all is called
+    /// render_all draws a separator between every this many cells. None for a flat grid
+    pub separator_every: Option<usize>,
     pub grid: Vec<Vec<Square>>,
+    /// The finished grid, if we know it. Either comes from the puzzle file or from solving it
+    pub solution: Option<Grid>,
+    /// Colors segments and squares can be, indexed by Color. Black is always first
+    pub palette: Vec<PaletteColor>,
+    /// Rows and cols wrap round, so a segment can run off the end and carry on from the start.
+    /// A segment that does that comes last in its hint
+    pub wrap: bool,
+    /// Clues covering pairs of neighbouring rows, see MegaClue
+    pub mega_rows: Vec<MegaClue>,
+    /// Clues covering pairs of neighbouring cols
+    pub mega_cols: Vec<MegaClue>,
 }
 
+/// Mega Picross clue, covering a pair of neighbouring lines at once. It gives the size of each
+/// group of filled squares in the pair, in order along the lines. Squares are in the same group
+/// when they touch side on, either along a line or across from one line to the other, but not
+/// corner to corner. Black only. The lines it covers have missing_clue as their own hints
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MegaClue {
+    /// The first of the 2 lines, the other is the one after it
+    pub first: usize,
+    pub groups: Vec<u32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Job {
     Row(usize),
     Col(usize),
 }
 
-pub struct Solver {
+impl Job {
+    /// Converts an index along this line into grid (row, col) coords
+    pub fn cell(&self, i: usize) -> (usize, usize) {
+        match self {
+            Job::Row(row) => (*row, i),
+            Job::Col(col) => (i, *col),
+        }
+    }
+
+    /// The line after this one
+    pub fn next(&self) -> Job {
+        match self {
+            Job::Row(row) => Job::Row(row + 1),
+            Job::Col(col) => Job::Col(col + 1),
+        }
+    }
+}
+
+/// How a single segment of a hint is doing against the line it's for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SegmentStatus {
+    /// Not pinned down yet
+    Open,
+    /// Every way of placing the hint puts this segment in the same spot, and it's all filled in
+    Done,
+    /// The line can't fit the hint at all anymore
+    Violated,
+}
+
+/// A single line's worth of logic the player can do next. Produced by `Game::next_deduction`
+#[derive(Debug)]
+pub struct Deduction {
+    pub job: Job,
+    /// Index in the line and the value that index must be
+    pub cells: Vec<(usize, Square)>,
+    /// Human readable reasons for the cells, roughly one per segment or gap involved
+    pub reasons: Vec<String>,
+}
+
+/// How far a run of line logic got
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Solved,
+    /// Nothing else can be worked out line by line, but nothing is wrong either
+    Stuck,
+    /// Some line can't fit its hint. The puzzle has no solution from this grid
+    Contradiction,
+}
+
+pub type Grid = Vec<Vec<Square>>;
+
+/// Which single line technique the solver uses
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LineLogic {
+    /// Overlap of the left-most and right-most placements. Quick, and what a person does first,
+    /// but it misses some things
+    #[default]
+    Overlap,
+    /// Everything that can be worked out from the line on its own. See refine_line_complete
+    Complete,
+}
+
+/// What line logic makes of a line: the refined line, whether it's solved and whether it
+/// changed. None when the hint can't fit
+pub type Refined = Option<(Vec<Square>, bool, bool)>;
+
+/// What the solver had to do to get where it got. Used to rate how hard a puzzle is
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SolveStats {
+    /// Sweeps over every row and col
+    pub passes: usize,
+    /// Line refinements that worked something out
+    pub lines_refined: usize,
+    /// Squares worked out by probing
+    pub probes: usize,
+    /// Guesses made while backtracking
+    pub guesses: usize,
+    /// Most guesses stacked on top of each other at once
+    pub max_depth: usize,
+}
+
+impl SolveStats {
+    /// Folds in the stats of a solver that ran from here, like a probe or a guess
+    fn absorb(&mut self, other: &SolveStats) {
+        self.passes += other.passes;
+        self.lines_refined += other.lines_refined;
+        self.probes += other.probes;
+        self.guesses += other.guesses;
+    }
+}
+
+/// Line logic, probing and backtracking over a Game. Generic over the line technique so they
+/// can be swapped out and compared, see LineSolver. The usual one caches whichever LineLogic
+/// it's on
+pub struct Solver<L = Cached<LineLogic>> {
     pub game: Game,
     // pub job_list: BinaryHeap<(i32, Job)>,
-    pub solved_rows: Vec<bool>,
-    pub solved_cols: Vec<bool>,
+    /// The game's rows and cols, see Game::topology
+    pub topology: Topology,
+    /// Which lines of topology are finished
+    pub solved: Vec<bool>,
+    pub stats: SolveStats,
+    pub line_logic: L,
 }
 
 impl Game {
-    pub fn new(col_hints: Vec<Hint>, row_hints: Vec<Hint>) -> Result<Game> {
+    /// Black and white puzzle
+    pub fn new(col_hints: Vec<Vec<u32>>, row_hints: Vec<Vec<u32>>) -> Result<Game> {
+        fn to_hints(hints: Vec<Vec<u32>>) -> Vec<Hint> {
+            hints
+                .into_iter()
+                .map(|hint| hint.into_iter().map(Segment::from).collect())
+                .collect()
+        }
+        Game::with_palette(
+            to_hints(col_hints),
+            to_hints(row_hints),
+            vec![PaletteColor::black()],
+        )
+    }
+
+    /// Puzzle whose segments can be any color in palette
+    pub fn with_palette(
+        col_hints: Vec<Hint>,
+        row_hints: Vec<Hint>,
+        palette: Vec<PaletteColor>,
+    ) -> Result<Game> {
         let cols = col_hints.len();
         let rows = row_hints.len();
+        if let Some(seg) = col_hints
+            .iter()
+            .chain(&row_hints)
+            .flatten()
+            .find(|seg| seg.color as usize >= palette.len())
+        {
+            bail!("segment uses color {} but there's no such color", seg.color);
+        }
+        // Col hints get checked the way the line solvers will see them
+        let transposed = col_hints.iter().flatten().map(Segment::transposed);
+        if let Some(seg) = row_hints
+            .iter()
+            .flatten()
+            .copied()
+            .chain(transposed)
+            .find(|seg| !seg.is_valid())
+        {
+            bail!(
+                "a segment of {} can't start and end with those shapes",
+                seg.len
+            );
+        }
 
         Ok(Game {
             rows,
@@ -54,10 +530,209 @@ impl Game {
             col_hints,
             row_hints,
             grid_pos: None,
+            separator_every: Some(5),
             grid: vec![vec![Square::Unknown; cols]; rows],
+            solution: None,
+            palette,
+            wrap: false,
+            mega_rows: vec![],
+            mega_cols: vec![],
         })
     }
 
+    /// Puts a mega clue over the line at job and the one after it, replacing their own hints
+    pub fn add_mega(&mut self, job: Job, groups: Vec<u32>) -> Result<()> {
+        let (first, lines) = match job {
+            Job::Row(first) => (first, self.rows),
+            Job::Col(first) => (first, self.cols),
+        };
+        if first + 1 >= lines {
+            bail!(
+                "a mega clue needs 2 lines but line {} is the last",
+                first + 1
+            );
+        }
+        if self.wrap {
+            bail!("mega clues don't wrap round");
+        }
+        if groups.contains(&0) {
+            bail!("a mega clue can't have an empty group");
+        }
+        let megas = match job {
+            Job::Row(_) => &mut self.mega_rows,
+            Job::Col(_) => &mut self.mega_cols,
+        };
+        if megas.iter().any(|mega| mega.first.abs_diff(first) < 2) {
+            bail!("line {} already has a mega clue", first + 1);
+        }
+        megas.push(MegaClue { first, groups });
+        let hints = match job {
+            Job::Row(_) => &mut self.row_hints,
+            Job::Col(_) => &mut self.col_hints,
+        };
+        hints[first] = Segment::missing_clue();
+        hints[first + 1] = Segment::missing_clue();
+        Ok(())
+    }
+
+    /// Every mega clue, with the first line it covers
+    pub fn megas(&self) -> impl Iterator<Item = (Job, &MegaClue)> {
+        let rows = self
+            .mega_rows
+            .iter()
+            .map(|mega| (Job::Row(mega.first), mega));
+        let cols = self
+            .mega_cols
+            .iter()
+            .map(|mega| (Job::Col(mega.first), mega));
+        rows.chain(cols)
+    }
+
+    /// The 2 lines under a mega clue
+    pub fn get_pair(&self, job: Job) -> (Vec<Square>, Vec<Square>) {
+        (self.get_line(job).1, self.get_line(job.next()).1)
+    }
+
+    /// Row (or col) mega clues
+    pub fn megas_along(&self, along_col: bool) -> &[MegaClue] {
+        match along_col {
+            false => &self.mega_rows,
+            true => &self.mega_cols,
+        }
+    }
+
+    /// Row (or col) hints the way they get shown, with the first line under each mega clue
+    /// showing its groups and the second line nothing. Cols aren't transposed
+    pub fn shown_hints(&self, along_col: bool) -> Vec<Hint> {
+        let mut hints = match along_col {
+            false => self.row_hints.clone(),
+            true => self.col_hints.clone(),
+        };
+        for mega in self.megas_along(along_col) {
+            hints[mega.first] = mega.groups.iter().copied().map(Segment::from).collect();
+            hints[mega.first + 1] = vec![];
+        }
+        hints
+    }
+
+    /// Text for each segment of shown_hints. The second line under a mega clue gets a ^ to say
+    /// it goes with the line before
+    pub fn hint_labels(&self, along_col: bool) -> Vec<Vec<String>> {
+        let megas = self.megas_along(along_col);
+        self.shown_hints(along_col)
+            .iter()
+            .enumerate()
+            .map(
+                |(i, hint)| match i > 0 && megas.iter().any(|mega| mega.first == i - 1) {
+                    true => vec!["^".to_string()],
+                    false => hint.iter().map(|seg| self.label(seg)).collect(),
+                },
+            )
+            .collect()
+    }
+
+    /// Whether the grid fits every clue, treating anything that isn't filled as a gap
+    pub fn is_solved(&self) -> bool {
+        (0..self.rows).all(|i| {
+            let (hint, line) = self.get_row(i);
+            self.matches_hint(&hint, &line)
+        }) && (0..self.cols).all(|i| {
+            let (hint, line) = self.get_col(i);
+            self.matches_hint(&hint, &line)
+        }) && self.megas_match()
+    }
+
+    /// Whether every mega clue matches its lines, treating anything that isn't filled as a gap
+    pub fn megas_match(&self) -> bool {
+        self.megas().all(|(job, mega)| {
+            let (first, second) = self.get_pair(job);
+            Game::pair_to_groups(&first, &second) == mega.groups
+        })
+    }
+
+    /// How a segment gets written in puzzle files and plain text: how it displays, then the
+    /// color's symbol unless it's black. EX: 3, 2r or ◢3◣
+    pub fn label(&self, seg: &Segment) -> String {
+        match seg.color {
+            0 => seg.to_string(),
+            color => format!("{}{}", seg, self.palette[color as usize].symbol),
+        }
+    }
+
+    /// Whether any hint has triangles in it
+    pub fn has_triangles(&self) -> bool {
+        self.row_hints
+            .iter()
+            .chain(&self.col_hints)
+            .flatten()
+            .any(|seg| seg.start.is_some() || seg.end.is_some())
+    }
+
+    /// Every value a square could end up as: each color, the triangles if the puzzle has any,
+    /// then empty
+    pub fn possible_squares(&self) -> Vec<Square> {
+        let triangles = match self.has_triangles() {
+            true => Corner::ALL.map(Square::Triangle).to_vec(),
+            false => vec![],
+        };
+        (0..self.palette.len())
+            .map(|color| Square::Filled(color as Color))
+            .chain(triangles)
+            .chain([Square::Empty])
+            .collect()
+    }
+
+    /// Character for a square in puzzle files and logs. _ for unknown, x for empty and the
+    /// palette's symbol for filled
+    pub fn symbol(&self, square: &Square) -> char {
+        match square {
+            Square::Unknown => '_',
+            Square::Filled(color) => self.palette[*color as usize].symbol,
+            Square::Triangle(corner) => corner.glyph(),
+            Square::Empty => 'x',
+        }
+    }
+
+    /// Fills in solution by running the solver on a blank copy of the puzzle, guessing if line
+    /// logic gets stuck. Only a unique solution counts. Returns whether a solution is known
+    /// afterwards
+    pub fn find_solution(&mut self) -> bool {
+        if self.solution.is_none() {
+            let mut blank = self.clone();
+            blank.grid = vec![vec![Square::Unknown; self.cols]; self.rows];
+            if let [only] = &Solver::new(blank).search(2)[..] {
+                self.solution = Some(only.clone());
+            }
+        }
+        self.solution.is_some()
+    }
+
+    /// Runs line logic on a blank copy of the puzzle. Returns the finished grid if that's enough
+    /// to solve it, which also means the solution is unique
+    pub fn line_solve(&self) -> Option<Vec<Vec<Square>>> {
+        let mut blank = self.clone();
+        blank.grid = vec![vec![Square::Unknown; self.cols]; self.rows];
+        let mut solver = Solver::new(blank);
+        (solver.solve(&mut None) == Outcome::Solved).then_some(solver.game.grid)
+    }
+
+    /// Every (row, col) where the grid has something marked that disagrees with the solution.
+    /// Unknown squares are never mistakes. Empty if there's no known solution
+    pub fn mistakes(&self) -> Vec<(usize, usize)> {
+        let Some(solution) = &self.solution else {
+            return vec![];
+        };
+        let mut mistakes = Vec::new();
+        for (i, (row, solution_row)) in zip(&self.grid, solution).enumerate() {
+            for (j, (square, answer)) in zip(row, solution_row).enumerate() {
+                if *square != Square::Unknown && square != answer {
+                    mistakes.push((i, j));
+                }
+            }
+        }
+        mistakes
+    }
+
     /// Gets a row as a line and its corresponding hint
     pub fn get_row(&self, i: usize) -> (Hint, Vec<Square>) {
         (self.row_hints[i].clone(), self.grid[i].clone())
@@ -67,23 +742,38 @@ impl Game {
         self.grid[i] = row;
     }
 
-    /// Gets a column as a line and its corresponding hint
+    /// Gets a column as a line and its corresponding hint. Both come transposed, so any triangles
+    /// in them read top to bottom the same way a row's read left to right
     pub fn get_col(&self, i: usize) -> (Hint, Vec<Square>) {
         (
-            self.col_hints[i].clone(),
-            self.grid.iter().map(|row| row[i].clone()).collect(),
+            self.col_hints[i].iter().map(Segment::transposed).collect(),
+            self.grid.iter().map(|row| row[i].transposed()).collect(),
         )
     }
 
+    /// Takes a transposed column, like get_col gives out
     pub fn set_col(&mut self, i: usize, col: Vec<Square>) {
         self.grid
             .iter_mut()
             .zip(col.iter())
-            .for_each(|(old, new)| old[i] = new.clone())
+            .for_each(|(old, new)| old[i] = new.transposed())
     }
 
     /// Checks if a line meets the criteria of a corresponding hint
-    pub fn check_line(hint: &[u32], line: &[Square]) -> bool {
+    pub fn check_line(hint: &[Segment], line: &[Square]) -> bool {
+        Game::hint_allows(hint, &Game::line_to_hint(line))
+    }
+
+    /// Whether the segments read off a line fit a hint, which might have hidden lengths in it or
+    /// be missing altogether
+    pub fn hint_allows(hint: &[Segment], found: &[Segment]) -> bool {
+        Segment::is_missing(hint)
+            || (hint.len() == found.len() && zip(hint, found).all(|(seg, found)| seg.allows(found)))
+    }
+
+    /// Reads the hint off of a line, treating anything that isn't filled as a gap. A change of
+    /// color, or a triangle that doesn't join up with its neighbor, starts a new segment
+    pub fn line_to_hint(line: &[Square]) -> Hint {
         // theres a lot of cases so heres some important ones
         // last segment is at the end of the line
         // last segment is not at the end of the line
@@ -96,80 +786,188 @@ impl Game {
         let mut segments: Hint = Vec::new(); // maybe a capacity here would be more efficient
         let mut curr_segment_len = 0u32; // This gets set to 0 when not in a segment (ya sure an
                                          // enum could encode whether it's in a segment or not, idc tho)
+        let mut curr_start = Square::Empty;
+        let mut last = Square::Empty;
+        // The segment from curr_start to last
+        let segment = |len: u32, start: &Square, last: &Square| {
+            let corner = |square: &Square| match square {
+                Square::Triangle(corner) => Some(*corner),
+                _ => None,
+            };
+            Segment {
+                len,
+                color: last.color().unwrap_or(0),
+                start: corner(start),
+                end: corner(last),
+                hidden: false,
+            }
+        };
 
         for square in line {
+            let joined = curr_segment_len != 0
+                && last.joins_right()
+                && square.joins_left()
+                && square.color() == last.color();
             match square {
-                Square::Filled => {
-                    curr_segment_len += 1;
+                Square::Filled(_) | Square::Triangle(_) if joined => curr_segment_len += 1,
+                Square::Filled(_) | Square::Triangle(_) => {
+                    // Anything that doesn't join up with the segment, like a different color or a
+                    // triangle facing the other way, starts a new one even right up against it
+                    if curr_segment_len != 0 {
+                        segments.push(segment(curr_segment_len, &curr_start, &last));
+                    }
+                    curr_segment_len = 1;
+                    curr_start = square.clone();
                 }
                 _ => {
                     if curr_segment_len != 0 {
-                        segments.push(curr_segment_len);
+                        segments.push(segment(curr_segment_len, &curr_start, &last));
                     }
                     curr_segment_len = 0
                 }
             }
+            last = square.clone();
         }
 
         if curr_segment_len != 0 {
-            segments.push(curr_segment_len);
+            segments.push(segment(curr_segment_len, &curr_start, &last));
+        }
+
+        segments
+    }
+
+    /// Same as line_to_hint, but for a line that wraps round. A segment running off the end and
+    /// carrying on from the start goes last, and a line that's all one segment is just that
+    pub fn cyclic_line_to_hint(line: &[Square]) -> Hint {
+        let n = line.len();
+        let joined = |before: &Square, square: &Square| {
+            before.joins_right() && square.joins_left() && before.color() == square.color()
+        };
+        // The first square that doesn't carry on a segment from the square before it, going round
+        // the end if it has to. Reading from there puts any wrapped segment in one piece at the end
+        match (0..n).find(|i| !joined(&line[(i + n - 1) % n], &line[*i])) {
+            Some(start) => {
+                let straightened: Vec<_> = line[start..]
+                    .iter()
+                    .chain(&line[..start])
+                    .cloned()
+                    .collect();
+                Game::line_to_hint(&straightened)
+            }
+            None => Game::line_to_hint(line),
+        }
+    }
+
+    /// Reads a hint off a line the way this puzzle does, wrapping round if it wraps
+    pub fn hint_of(&self, line: &[Square]) -> Hint {
+        match self.wrap {
+            true => Game::cyclic_line_to_hint(line),
+            false => Game::line_to_hint(line),
         }
+    }
 
-        segments == hint
+    /// check_line, but wrapping round if the puzzle wraps
+    pub fn matches_hint(&self, hint: &[Segment], line: &[Square]) -> bool {
+        Game::hint_allows(hint, &self.hint_of(line))
+    }
+
+    /// Makes a puzzle out of a finished picture. The hints are read off the grid and the grid
+    /// becomes the known solution, leaving the playing grid blank. Any colors past black get
+    /// stand-in palette entries, so set the real palette after for color pictures
+    pub fn from_solution(solution: Vec<Vec<Square>>) -> Game {
+        Game::read_picture(solution, false)
+    }
+
+    /// Same as from_solution, but with rows and cols that wrap round
+    pub fn wrapped_from_solution(solution: Vec<Vec<Square>>) -> Game {
+        Game::read_picture(solution, true)
+    }
+
+    fn read_picture(solution: Vec<Vec<Square>>, wrap: bool) -> Game {
+        let to_hint = match wrap {
+            true => Game::cyclic_line_to_hint,
+            false => Game::line_to_hint,
+        };
+        let cols = solution.first().map_or(0, |row| row.len());
+        let row_hints = solution.iter().map(|row| to_hint(row)).collect();
+        let col_hints = (0..cols)
+            .map(|i| {
+                let col: Vec<_> = solution.iter().map(|row| row[i].transposed()).collect();
+                to_hint(&col).iter().map(Segment::transposed).collect()
+            })
+            .collect();
+        let colors = solution
+            .iter()
+            .flatten()
+            .filter_map(|square| match square {
+                Square::Filled(color) => Some(*color),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0);
+        let palette = std::iter::once(PaletteColor::black())
+            .chain((1..=colors).map(|color| PaletteColor {
+                symbol: (b'a' + color - 1) as char,
+                rgb: (128, 128, 128),
+            }))
+            .collect();
+        let mut game =
+            Game::with_palette(col_hints, row_hints, palette).expect("palette covers every color");
+        game.solution = Some(solution);
+        game.wrap = wrap;
+        game
     }
 
     /// Given the current state of a line and its hint, return a vec of the segments placed as far
     /// left as possible. The values represent the index in the Hint vec that they correspond to
-    pub fn place_all_left(hint: &[u32], line: &[Square]) -> Option<Vec<SegmentPlacement>> {
+    pub fn place_all_left(hint: &[Segment], line: &[Square]) -> Option<Vec<SegmentPlacement>> {
         /// Check if a segment fits starting at start_index
         /// Does not check if it touches other filled spaces
-        /// Only checks if there are x's in the way
-        fn can_seg_be_placed(segment: u32, line: &[Square], start_index: usize) -> bool {
+        /// Only checks if there are x's (or other colors) in the way
+        fn can_seg_be_placed(segment: Segment, line: &[Square], start_index: usize) -> bool {
             // First check that the segment will fit within the bounds of the line
-            let segment = segment as usize; // segment gets used a lot with start_loc to index
+            let len = segment.len as usize; // len gets used a lot with start_loc to index
                                             // line, so we shadow it with this conversion to usize
-            if segment > line.len() {
+            if len > line.len() {
                 return false;
             }
 
-            if line[start_index..start_index + segment] // TODO: Check if this needs + 1 (prob no)
+            line[start_index..start_index + len] // TODO: Check if this needs + 1 (prob no)
                 .iter()
-                .any(|square| *square == Square::Empty)
-            {
-                return false;
-            }
-            true
+                .enumerate()
+                .all(|(i, square)| *square == Square::Unknown || *square == segment.square_at(i))
         }
 
         /// Places a segment as far left as possible. Returns the index of the left-most index the
         /// segment can start at if it can be placed, else None
         /// Takes an index to start the search at
         fn place_segment_left(
-            segment: u32,
+            segment: Segment,
             line: &[Square],
             search_start_index: usize,
         ) -> Option<usize> {
             // Loop through every possible starting pos, which starts at start_index and goes up to
             // 1 segment length before the end of the line since it can't fit anywhere after that.
-            (search_start_index..line.len() + 1 - segment as usize)
+            (search_start_index..line.len() + 1 - segment.len as usize)
                 .find(|i| can_seg_be_placed(segment, line, *i))
         }
 
-        fn place_segment(segment: u32, line: &mut [Square], index: usize) {
-            line[index..index + segment as usize]
+        fn place_segment(segment: Segment, line: &mut [Square], index: usize) {
+            line[index..index + segment.len as usize]
                 .iter_mut()
-                .for_each(|square| *square = Square::Filled);
+                .enumerate()
+                .for_each(|(i, square)| *square = segment.square_at(i));
         }
 
         fn place_in_segment_placements(
             // Segment placements is a representation of the Line that includes data of what
             // segment a cell is a part of
             placements: &mut [SegmentPlacement],
-            segment: u32,
+            segment: Segment,
             segment_index: usize,
             pos: usize,
         ) {
-            placements[pos..pos + segment as usize]
+            placements[pos..pos + segment.len as usize]
                 .iter_mut()
                 .for_each(|cell| *cell = Some(segment_index));
         }
@@ -177,7 +975,7 @@ impl Game {
         /// Given a slice of line indexes for each hint, place them on the line
         fn place_segment_positions(
             positions: &[usize],
-            hint: &[u32],
+            hint: &[Segment],
             size: usize,
         ) -> Vec<SegmentPlacement> {
             let mut segment_placements: Vec<SegmentPlacement> = vec![None; size];
@@ -193,7 +991,7 @@ impl Game {
         /// starting at the start_index
         /// Returns an accumulating line of positions for each segment to be placed at
         fn rec_place_left(
-            hint: &[u32],
+            hint: &[Segment],
             hint_index: usize,
             line: &[Square],
             start_index: usize,
@@ -213,7 +1011,7 @@ impl Game {
 
             // This may be able to be optimized to be O(n) instead of O(n^2) by making x
             // checking and validity checking happen in the same loop
-            for i in start_index..line.len() + 1 - *seg_to_place as usize {
+            for i in start_index..line.len() + 1 - seg_to_place.len as usize {
                 // TODO: Check for off by 1
                 let placement_index = match place_segment_left(*seg_to_place, line, i) {
                     None => {
@@ -227,7 +1025,10 @@ impl Game {
                     hint,
                     hint_index + 1,
                     &new_line,
-                    placement_index + *seg_to_place as usize + 1, // TODO: No fuckin way this is correct (Update, I was right, this comment is being left bc its funny)
+                    // Only a segment of the same color needs a gap before it
+                    placement_index
+                        + seg_to_place.len as usize
+                        + needs_gap(hint, hint_index) as usize, // TODO: No fuckin way this is correct (Update, I was right, this comment is being left bc its funny)
                 ) {
                     None => continue,
                     Some(partial_line) => partial_line,
@@ -247,11 +1048,10 @@ impl Game {
         positions.map(|positions| place_segment_positions(&positions, hint, line.len()))
     }
 
-    pub fn place_all_right(hint: &[u32], line: &[Square]) -> Option<Vec<SegmentPlacement>> {
-        let mut reverse_line = line.to_vec();
-        reverse_line.reverse();
-        let mut reverse_hint = hint.to_vec();
-        reverse_hint.reverse();
+    pub fn place_all_right(hint: &[Segment], line: &[Square]) -> Option<Vec<SegmentPlacement>> {
+        // Triangles face the other way once the line's flipped
+        let reverse_line: Vec<_> = line.iter().rev().map(Square::mirrored).collect();
+        let reverse_hint: Vec<_> = hint.iter().rev().map(Segment::reversed).collect();
 
         let mut placements = Game::place_all_left(&reverse_hint, &reverse_line);
         if let Some(placements) = placements.as_mut() {
@@ -266,9 +1066,16 @@ impl Game {
         placements
     }
 
-    pub fn refine_line(line: &[Square], hint: &[u32]) -> (Vec<Square>, bool, bool) {
-        let left_sol = Game::place_all_left(hint, line).expect("No left solution found");
-        let right_sol = Game::place_all_right(hint, line).expect("No right solution found");
+    /// Returns the refined line, whether it is fully solved, and whether anything changed.
+    /// None means the line contradicts its hint (there's no way to place the segments at all)
+    pub fn refine_line(line: &[Square], hint: &[Segment]) -> Option<(Vec<Square>, bool, bool)> {
+        // A segment can't overlap with itself when there's no telling how long it is, so those
+        // lines get the full treatment
+        if hint.iter().any(|seg| seg.hidden) {
+            return Game::refine_line_complete(line, hint);
+        }
+        let left_sol = Game::place_all_left(hint, line)?;
+        let right_sol = Game::place_all_right(hint, line)?;
 
         // let mut new_line = vec![Square::Unknown; line.len()]; // Might be interesting for later
         // to try just cloning the original
@@ -293,21 +1100,38 @@ impl Game {
         // Ditto for right sol
         let mut right_sol_next_seg = 0;
         for i in 0..line.len() {
-            // If passing a segment, increment the next seg indicator
-            if i > 0 && left_sol[i - 1].is_some() && left_sol[i].is_none() {
-                left_sol_next_seg += 1;
+            // Segments of different colors can touch, so go by the last segment seen rather than
+            // by leaving one
+            if let Some(seg) = left_sol[i] {
+                left_sol_next_seg = seg + 1;
             }
-
-            if i > 0 && right_sol[i - 1].is_some() && right_sol[i].is_none() {
-                right_sol_next_seg += 1;
+            if let Some(seg) = right_sol[i] {
+                right_sol_next_seg = seg + 1;
             }
 
-            // If they are equal and Some, there is an overlap
-            if left_sol[i].is_some() && (left_sol[i] == right_sol[i]) {
-                if new_line[i] != Square::Filled {
+            // If they are equal and Some, there is an overlap. With triangle ends the overlap only
+            // says what the square is if every placement in between puts the same part of the
+            // segment on it
+            if let (Some(seg), true) = (left_sol[i], left_sol[i] == right_sol[i]) {
+                let start_of = |placements: &[SegmentPlacement]| {
+                    i + 1
+                        - placements[..=i]
+                            .iter()
+                            .rev()
+                            .take_while(|p| **p == Some(seg))
+                            .count()
+                };
+                let filled = hint[seg].square_at(i - start_of(&left_sol));
+                if (start_of(&left_sol)..=start_of(&right_sol))
+                    .any(|start| hint[seg].square_at(i - start) != filled)
+                {
+                    solved = false;
+                    continue;
+                }
+                if new_line[i] != filled {
                     changed = true;
                 }
-                new_line[i] = Square::Filled;
+                new_line[i] = filled;
             }
             // If both are in a gap and they are preceding the same next segment, its empty
             else if left_sol[i].is_none()
@@ -322,108 +1146,901 @@ impl Game {
                 solved = false;
             }
         }
-        (new_line, solved, changed)
+        Some((new_line, solved, changed))
     }
-}
 
-impl Solver {
-    pub fn new(game: Game) -> Self {
-        let rows = game.rows;
-        let cols = game.cols;
-        Solver {
-            game,
-            solved_rows: vec![false; rows],
-            solved_cols: vec![false; cols],
+    /// Same deal as refine_line, but finds everything the line can tell on its own: a square gets
+    /// filled (or crossed) if every valid placement of the whole hint fills (or crosses) it. Done
+    /// with a table of which prefixes and suffixes of the line can hold which segments, so it
+    /// stays quick on long lines. Hidden lengths get tried at every length they could be
+    pub fn refine_line_complete(
+        line: &[Square],
+        hint: &[Segment],
+    ) -> Option<(Vec<Square>, bool, bool)> {
+        let n = line.len();
+        let k = hint.len();
+        let gap = |seg: usize| needs_gap(hint, seg) as usize;
+        // Lengths segment seg could be if it starts at start
+        let lengths = |seg: usize, start: usize| match hint[seg].hidden {
+            true => 1..=n - start,
+            false => hint[seg].len as usize..=hint[seg].len as usize,
+        };
+        // Whether segment seg can go len squares from start: nothing under it but unknowns and
+        // the squares it needs, and room for its gap if it needs one
+        let fits = |seg: usize, start: usize, len: usize| {
+            let end = start + len;
+            end + gap(seg) <= n
+                && line[start..end].iter().enumerate().all(|(i, square)| {
+                    *square == Square::Unknown || *square == hint[seg].square_at(i)
+                })
+                && (gap(seg) == 0 || !line[end].is_filled())
+        };
+        // Where the next thing can go after segment seg, skipping its gap
+        let after = |seg: usize, start: usize, len: usize| start + len + gap(seg);
+        let is_filled = |p: usize| line[p].is_filled();
+
+        // before[j][p]: line[..p] can hold exactly the first j segments
+        let mut before = vec![vec![false; n + 1]; k + 1];
+        before[0][0] = true;
+        for j in 0..=k {
+            for p in 0..n {
+                if !before[j][p] {
+                    continue;
+                }
+                if !is_filled(p) {
+                    before[j][p + 1] = true;
+                }
+                if j == k {
+                    continue;
+                }
+                for len in lengths(j, p) {
+                    if fits(j, p, len) {
+                        before[j + 1][after(j, p, len)] = true;
+                    }
+                }
+            }
+        }
+        // from[j][p]: line[p..] can hold exactly the segments from j on
+        let mut from = vec![vec![false; n + 1]; k + 1];
+        from[k][n] = true;
+        for j in (0..=k).rev() {
+            for p in (0..n).rev() {
+                from[j][p] = (!is_filled(p) && from[j][p + 1])
+                    || (j < k
+                        && lengths(j, p)
+                            .any(|len| fits(j, p, len) && from[j + 1][after(j, p, len)]));
+            }
+        }
+        if !from[0][0] {
+            return None;
         }
-    }
 
-    pub fn solve(&mut self, file: &mut Option<&mut File>) {
-        loop {
-            let mut puzzle_changed = false;
-            for i in 0..self.game.rows {
-                if self.solved_rows[i] {
+        /// What a square has been seen filled with across every valid placement
+        #[derive(Clone, PartialEq)]
+        enum Fill {
+            Never,
+            Only(Square),
+            Mixed,
+        }
+        let mut fill = vec![Fill::Never; n];
+        let mut can_empty = vec![false; n];
+        for j in 0..=k {
+            for p in 0..n {
+                if !before[j][p] {
+                    continue;
+                }
+                if !is_filled(p) && from[j][p + 1] {
+                    can_empty[p] = true;
+                }
+                if j == k {
                     continue;
                 }
+                for len in lengths(j, p) {
+                    if !fits(j, p, len) || !from[j + 1][after(j, p, len)] {
+                        continue;
+                    }
+                    let end = p + len;
+                    for (offset, cell) in fill[p..end].iter_mut().enumerate() {
+                        let square = hint[j].square_at(offset);
+                        *cell = match cell {
+                            Fill::Never => Fill::Only(square),
+                            Fill::Only(seen) if *seen == square => continue,
+                            _ => Fill::Mixed,
+                        };
+                    }
+                    if gap(j) == 1 {
+                        can_empty[end] = true;
+                    }
+                }
+            }
+        }
 
-                let (hint, line) = self.game.get_row(i);
-                let (new_row, solved, line_changed) = Game::refine_line(&line, &hint);
-                self.solved_rows[i] = solved;
-                puzzle_changed |= line_changed;
-                if !line_changed {
+        let mut new_line = line.to_vec();
+        let mut solved = true;
+        let mut changed = false;
+        for (i, square) in new_line.iter_mut().enumerate() {
+            let known = match (&fill[i], can_empty[i]) {
+                (Fill::Only(filled), false) => filled.clone(),
+                (Fill::Never, true) => Square::Empty,
+                _ => {
+                    solved = false;
                     continue;
                 }
-                if let Some(f) = file.as_mut() {
-                    f.write_all(i.to_string().as_bytes()).unwrap();
-                    f.write_all(b" row ").unwrap();
-                    f.write_all(
-                        new_row
-                            .iter()
-                            .map(|square| match square {
-                                Square::Unknown => "_",
-                                Square::Filled => "o",
-                                Square::Empty => "x",
-                            })
-                            .collect::<Vec<_>>()
-                            .join(" ")
-                            .as_bytes(),
-                    )
-                    .unwrap();
-                    f.write_all(b"\n").unwrap();
+            };
+            changed |= *square != known;
+            *square = known;
+        }
+        Some((new_line, solved, changed))
+    }
+
+    /// refine_line (or whatever line technique refine is) for a line that wraps round.
+    /// Either nothing runs off the end, or the last segment does with some of its squares at the
+    /// end and the rest at the start. Each of those gets straightened out into a normal line for
+    /// refine, and whatever they all agree on is what the wrapped line can tell
+    pub fn refine_line_cyclic(
+        line: &[Square],
+        hint: &[Segment],
+        mut refine: impl FnMut(&[Square], &[Segment]) -> Refined,
+    ) -> Option<(Vec<Square>, bool, bool)> {
+        let n = line.len();
+        let k = hint.len();
+        // Whether after butts up against before with no gap, going round the end
+        let joins = |before: &Segment, after: &Segment| {
+            before.color == after.color
+                && before.square_at(before.len as usize - 1).joins_right()
+                && after.square_at(0).joins_left()
+        };
+
+        /// The line rotated left by shift, the hint to read it with, and squares that way needs on
+        /// top of what the line has already
+        struct Way {
+            shift: usize,
+            hint: Hint,
+            needs: Vec<(usize, Square)>,
+        }
+        let mut ways: Vec<Way> = Vec::new();
+        // Nothing runs off the end. If the last segment would join up with the first one going
+        // round, they can't be at both ends at once. A line that's one segment the whole way is
+        // fine though
+        match k > 0 && joins(&hint[k - 1], &hint[0]) && !(k == 1 && hint[0].len as usize == n) {
+            true => {
+                for end in [0, n - 1] {
+                    ways.push(Way {
+                        shift: 0,
+                        hint: hint.to_vec(),
+                        needs: vec![(end, Square::Empty)],
+                    });
+                }
+            }
+            false => ways.push(Way {
+                shift: 0,
+                hint: hint.to_vec(),
+                needs: vec![],
+            }),
+        }
+        // The last segment runs off the end with tail of its squares there. Rotating so it starts
+        // the line puts it first. A segment that's the whole line only looks any different
+        // rotated if it has triangles on the ends, but then it can start anywhere. A hidden
+        // length could have any tail, and has to carry on at least one square past the end
+        let rotates = |last: &&Segment| {
+            (last.len as usize) < n
+                || (last.len as usize == n && (last.start.is_some() || last.end.is_some()))
+        };
+        if let Some(last) = hint.last().filter(rotates) {
+            let rotated_hint: Hint = std::iter::once(*last)
+                .chain(hint[..k - 1].iter().copied())
+                .collect();
+            let tails = match last.hidden {
+                true => 1..n,
+                false => 1..last.len as usize,
+            };
+            for tail in tails {
+                let covered = match last.hidden {
+                    true => tail + 1,
+                    false => last.len as usize,
                 };
-                self.game.set_row(i, new_row);
+                let mut needs: Vec<_> = (0..covered).map(|i| (i, last.square_at(i))).collect();
+                if k > 1 && joins(&hint[k - 2], last) {
+                    needs.push((n - 1, Square::Empty));
+                }
+                ways.push(Way {
+                    shift: n - tail,
+                    hint: rotated_hint.clone(),
+                    needs,
+                });
             }
+        }
 
-            for i in 0..self.game.cols {
-                if self.solved_cols[i] {
-                    continue;
+        let mut agreed: Option<Vec<Square>> = None;
+        for Way { shift, hint, needs } in ways {
+            let mut rotated: Vec<_> = (0..n).map(|i| line[(i + shift) % n].clone()).collect();
+            let possible = needs.into_iter().all(|(i, square)| {
+                let fits = rotated[i] == Square::Unknown || rotated[i] == square;
+                rotated[i] = square;
+                fits
+            });
+            let Some((refined, _, _)) = possible.then(|| refine(&rotated, &hint)).flatten() else {
+                continue;
+            };
+            let mut unrotated = vec![Square::Unknown; n];
+            for (i, square) in refined.into_iter().enumerate() {
+                unrotated[(i + shift) % n] = square;
+            }
+            agreed = Some(Game::agree(agreed, unrotated));
+        }
+
+        let new_line = agreed?;
+        let solved = !new_line.contains(&Square::Unknown);
+        let changed = new_line != line;
+        Some((new_line, solved, changed))
+    }
+
+    /// Reads a mega clue off a pair of lines, treating anything that isn't filled as a gap.
+    /// Since the squares across from each other touch, each spot along the pair can only ever
+    /// be in one group, so groups are runs of spots, broken by a spot with nothing filled or
+    /// by two spots that only meet corner to corner
+    pub fn pair_to_groups(first: &[Square], second: &[Square]) -> Vec<u32> {
+        let mut groups = vec![];
+        let mut run = 0;
+        let mut prev = 0;
+        for (a, b) in zip(first, second) {
+            let spot = a.is_filled() as u8 | (b.is_filled() as u8) << 1;
+            if run > 0 && prev & spot == 0 {
+                groups.push(run);
+                run = 0;
+            }
+            run += spot.count_ones();
+            prev = spot;
+        }
+        if run > 0 {
+            groups.push(run);
+        }
+        groups
+    }
+
+    /// refine_line_complete for the 2 lines under a mega clue at once. Works spot by spot along
+    /// the pair, keeping every state that can be reached (groups started, size of the group
+    /// still going, and which squares the last spot had filled), then keeps only the ones that
+    /// can still get to the end with every group placed. Gives back both lines, whether they're
+    /// solved and whether either changed. None when the groups can't fit
+    pub fn refine_pair(
+        first: &[Square],
+        second: &[Square],
+        groups: &[u32],
+    ) -> Option<(Vec<Square>, Vec<Square>, bool, bool)> {
+        type State = (usize, u32, u8);
+        let fits = |square: &Square, filled: bool| match square {
+            Square::Unknown => true,
+            Square::Empty => !filled,
+            Square::Filled(0) => filled,
+            _ => false,
+        };
+        // What a spot could be, 1 for the first line filled and 2 for the second
+        let options = |i: usize| {
+            (0..4u8).filter(move |spot| {
+                fits(&first[i], spot & 1 != 0) && fits(&second[i], spot & 2 != 0)
+            })
+        };
+        let step =
+            |(started, run, prev): State, spot: u8| -> Option<State> {
+                let size = spot.count_ones();
+                // A group can only end once it's the right size
+                let can_end = run == 0 || groups[started - 1] == run;
+                match spot {
+                    0 => can_end.then_some((started, 0, 0)),
+                    _ if run > 0 && prev & spot != 0 => {
+                        (run + size <= groups[started - 1]).then_some((started, run + size, spot))
+                    }
+                    _ => (can_end && started < groups.len() && size <= groups[started])
+                        .then_some((started + 1, size, spot)),
                 }
+            };
 
-                let (hint, line) = self.game.get_col(i);
-                let (new_col, solved, line_changed) = Game::refine_line(&line, &hint);
-                self.solved_cols[i] = solved;
-                puzzle_changed |= line_changed;
-                if !line_changed {
-                    continue;
+        let n = first.len();
+        let mut reached: Vec<HashSet<State>> = vec![HashSet::from([(0, 0, 0)])];
+        for i in 0..n {
+            let next = reached[i]
+                .iter()
+                .flat_map(|state| options(i).filter_map(move |spot| step(*state, spot)))
+                .collect();
+            reached.push(next);
+        }
+        // Backwards, keeping only states that lead somewhere and noting what each spot could be
+        let mut good: HashSet<State> = reached[n]
+            .iter()
+            .filter(|(started, run, _)| {
+                *started == groups.len() && (*run == 0 || groups[started - 1] == *run)
+            })
+            .copied()
+            .collect();
+        let mut spots = vec![0u8; n];
+        for i in (0..n).rev() {
+            let mut still_good = HashSet::new();
+            for state in &reached[i] {
+                for spot in options(i) {
+                    if step(*state, spot).is_some_and(|next| good.contains(&next)) {
+                        still_good.insert(*state);
+                        spots[i] |= 1 << spot;
+                    }
                 }
-                if let Some(f) = file.as_mut() {
-                    f.write_all(i.to_string().as_bytes()).unwrap();
-                    f.write_all(b" col ").unwrap();
-                    f.write_all(
-                        new_col
-                            .iter()
-                            .map(|square| match square {
-                                Square::Unknown => "_",
-                                Square::Filled => "o",
-                                Square::Empty => "x",
-                            })
-                            .collect::<Vec<_>>()
-                            .join(" ")
-                            .as_bytes(),
-                    )
-                    .unwrap();
-                    f.write_all(b"\n").unwrap();
+            }
+            good = still_good;
+        }
+        if good.is_empty() {
+            return None;
+        }
+
+        // A square is settled if every spot that's still possible agrees on it
+        let settle = |line: &[Square], bit: u8| -> Vec<Square> {
+            zip(line, &spots)
+                .map(|(square, possible)| {
+                    let filled = (0..4).any(|spot| possible & (1 << spot) != 0 && spot & bit != 0);
+                    let empty = (0..4).any(|spot| possible & (1 << spot) != 0 && spot & bit == 0);
+                    match (filled, empty) {
+                        (true, false) => Square::Filled(0),
+                        (false, true) => Square::Empty,
+                        _ => square.clone(),
+                    }
+                })
+                .collect()
+        };
+        let (new_first, new_second) = (settle(first, 1), settle(second, 2));
+        let solved =
+            !new_first.contains(&Square::Unknown) && !new_second.contains(&Square::Unknown);
+        let changed = new_first != first || new_second != second;
+        Some((new_first, new_second, solved, changed))
+    }
+
+    /// Folds another way a line could go into what every way so far agrees on. Squares they
+    /// don't agree on go back to unknown
+    pub fn agree(agreed: Option<Vec<Square>>, way: Vec<Square>) -> Vec<Square> {
+        let Some(mut agreed) = agreed else {
+            return way;
+        };
+        for (square, other) in zip(&mut agreed, way) {
+            if *square != other {
+                *square = Square::Unknown;
+            }
+        }
+        agreed
+    }
+
+    /// Runs line_solver on a line of this puzzle, wrapping round if it wraps
+    pub fn refine(
+        &self,
+        line: &[Square],
+        hint: &[Segment],
+        line_solver: &mut impl LineSolver,
+    ) -> Option<(Vec<Square>, bool, bool)> {
+        // Nothing to go on without a clue, the other direction has to do it all
+        if Segment::is_missing(hint) {
+            let solved = !line.contains(&Square::Unknown);
+            return Some((line.to_vec(), solved, false));
+        }
+        match self.wrap {
+            true => Game::refine_line_cyclic(line, hint, |line, hint| {
+                line_solver.refine_line(line, hint)
+            }),
+            false => line_solver.refine_line(line, hint),
+        }
+    }
+
+    /// Whether a line's logic can be put segment by segment, which takes fixed lengths and not
+    /// wrapping round
+    pub fn by_segment(&self, hint: &[Segment]) -> bool {
+        !self.wrap && hint.iter().all(|seg| !seg.hidden)
+    }
+
+    /// segment_statuses for a line of this puzzle. Lines that can't go by_segment are all their
+    /// segments done once the line's filled in right, or all violated once it can't be
+    pub fn clue_statuses(&self, hint: &[Segment], line: &[Square]) -> Vec<SegmentStatus> {
+        if self.by_segment(hint) {
+            return Game::segment_statuses(hint, line);
+        }
+        let status = match self.refine(line, hint, &mut Overlap) {
+            None => SegmentStatus::Violated,
+            Some(_) if !line.contains(&Square::Unknown) => SegmentStatus::Done,
+            Some(_) => SegmentStatus::Open,
+        };
+        vec![status; hint.len()]
+    }
+
+    /// Works out the status of each segment in hint for the current state of line.
+    /// A segment is Done when the left-most and right-most placements agree on where it goes and
+    /// the line already has it filled in there. Since every valid placement of a segment is
+    /// somewhere between those two, that means it can't be anywhere else.
+    pub fn segment_statuses(hint: &[Segment], line: &[Square]) -> Vec<SegmentStatus> {
+        let (Some(left_sol), Some(right_sol)) = (
+            Game::place_all_left(hint, line),
+            Game::place_all_right(hint, line),
+        ) else {
+            return vec![SegmentStatus::Violated; hint.len()];
+        };
+
+        fn start_of(placements: &[SegmentPlacement], seg: usize) -> Option<usize> {
+            placements.iter().position(|p| *p == Some(seg))
+        }
+
+        hint.iter()
+            .enumerate()
+            .map(
+                |(seg, segment)| match (start_of(&left_sol, seg), start_of(&right_sol, seg)) {
+                    (Some(left), Some(right))
+                        if left == right
+                            && line[left..left + segment.len as usize]
+                                .iter()
+                                .enumerate()
+                                .all(|(i, square)| *square == segment.square_at(i)) =>
+                    {
+                        SegmentStatus::Done
+                    }
+                    _ => SegmentStatus::Open,
+                },
+            )
+            .collect()
+    }
+
+    /// Gets a line and its hint by job
+    pub fn get_line(&self, job: Job) -> (Hint, Vec<Square>) {
+        match job {
+            Job::Row(i) => self.get_row(i),
+            Job::Col(i) => self.get_col(i),
+        }
+    }
+
+    /// The grid as a Topology, with cells row by row (row * cols + col). Every row comes first
+    /// and then every col, which reads transposed like get_col
+    pub fn topology(&self) -> Topology {
+        let rows = (0..self.rows).map(|row| {
+            let cells = (0..self.cols).map(|col| row * self.cols + col).collect();
+            Line::new(cells, self.row_hints[row].clone())
+        });
+        let cols = (0..self.cols).map(|col| Line {
+            cells: (0..self.rows).map(|row| row * self.cols + col).collect(),
+            hint: self.get_col(col).0,
+            transposed: true,
+        });
+        Topology {
+            lines: rows.chain(cols).collect(),
+        }
+    }
+
+    /// Which row or col line i of topology is
+    pub fn line_job(&self, i: usize) -> Job {
+        match i.checked_sub(self.rows) {
+            None => Job::Row(i),
+            Some(col) => Job::Col(col),
+        }
+    }
+
+    /// Takes a line the way get_line gives it out
+    pub fn set_line(&mut self, job: Job, line: Vec<Square>) {
+        match job {
+            Job::Row(i) => self.set_row(i, line),
+            Job::Col(i) => self.set_col(i, line),
+        }
+    }
+
+    /// Finds the cheapest deduction available from the current grid using only single line logic.
+    /// Cheapest here means the line with the fewest unknown squares left, since those are the
+    /// easiest for a person to spot. Ties go to whichever line reveals more squares.
+    /// Returns Err with the offending line if some line can't fit its hint anymore, which in play
+    /// mode means the player made a mistake somewhere in it.
+    pub fn next_deduction(&self) -> Result<Option<Deduction>, Job> {
+        let jobs = (0..self.rows)
+            .map(Job::Row)
+            .chain((0..self.cols).map(Job::Col));
+
+        let mut best: Option<(usize, Deduction)> = None;
+        for job in jobs {
+            let (hint, line) = self.get_line(job);
+            let (new_line, _, changed) = self.refine(&line, &hint, &mut Overlap).ok_or(job)?;
+            if !changed {
+                continue;
+            }
+            let unknowns = line.iter().filter(|s| **s == Square::Unknown).count();
+            let cells: Vec<_> = zip(&line, new_line)
+                .enumerate()
+                .filter(|(_, (old, new))| *old != new)
+                .map(|(i, (_, new))| (i, new))
+                .collect();
+            let better = match &best {
+                None => true,
+                Some((best_unknowns, best_deduction)) => {
+                    unknowns < *best_unknowns
+                        || (unknowns == *best_unknowns && cells.len() > best_deduction.cells.len())
+                }
+            };
+            if better {
+                let reasons = match self.by_segment(&hint) {
+                    true => Game::explain_line(&hint, &line, &cells),
+                    false => {
+                        vec!["Every way the clue still fits the line agrees on them".to_string()]
+                    }
                 };
-                self.game.set_col(i, new_col);
+                best = Some((
+                    unknowns,
+                    Deduction {
+                        job,
+                        cells,
+                        reasons,
+                    },
+                ));
             }
+        }
+        Ok(best.map(|(_, deduction)| deduction))
+    }
 
-            // Check if all rows and cols are solved
-            if self.solved_rows.iter().all(|val| *val) && self.solved_cols.iter().all(|val| *val) {
-                break;
+    /// Puts into words why refine_line came up with the given cells. Filled cells come from a
+    /// segment overlapping with itself between its left-most and right-most placements, and empty
+    /// cells come from gaps that no segment can reach.
+    pub fn explain_line(
+        hint: &[Segment],
+        line: &[Square],
+        cells: &[(usize, Square)],
+    ) -> Vec<String> {
+        let Some(left_sol) = Game::place_all_left(hint, line) else {
+            return vec![];
+        };
+        // Group the cells up so each segment gets one reason instead of one per cell
+        let mut filled: Vec<(usize, Vec<usize>)> = Vec::new();
+        let mut empty: Vec<usize> = Vec::new();
+        for (i, square) in cells {
+            match (square, left_sol[*i]) {
+                (Square::Filled(_) | Square::Triangle(_), Some(seg)) => {
+                    match filled.iter_mut().find(|(s, _)| *s == seg) {
+                        Some((_, positions)) => positions.push(*i),
+                        None => filled.push((seg, vec![*i])),
+                    }
+                }
+                _ => empty.push(*i),
+            }
+        }
+
+        fn positions_to_string(positions: &[usize]) -> String {
+            positions
+                .iter()
+                .map(|p| (p + 1).to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        }
+
+        let mut reasons: Vec<String> = filled
+            .iter()
+            .map(|(seg, positions)| {
+                format!(
+                    "The {} pushed as far left and as far right as it goes still covers {}",
+                    hint[*seg],
+                    positions_to_string(positions)
+                )
+            })
+            .collect();
+        if !empty.is_empty() {
+            reasons.push(format!(
+                "No segment can reach {}",
+                positions_to_string(&empty)
+            ));
+        }
+        reasons
+    }
+}
+
+impl Solver {
+    pub fn new(game: Game) -> Self {
+        Solver::with_line_solver(game, Cached::new(LineLogic::default()))
+    }
+}
+
+impl<L: LineSolver + Clone> Solver<L> {
+    pub fn with_line_solver(game: Game, line_solver: L) -> Self {
+        let topology = game.topology();
+        let lines = topology.lines.len();
+        Solver {
+            game,
+            topology,
+            solved: vec![false; lines],
+            stats: SolveStats::default(),
+            line_logic: line_solver,
+        }
+    }
+
+    /// Solver that picks up from the current grid with the same line logic but fresh stats, for
+    /// trying things out on
+    fn branch(&self) -> Solver<L> {
+        Solver {
+            game: self.game.clone(),
+            topology: self.topology.clone(),
+            solved: vec![false; self.solved.len()],
+            stats: SolveStats::default(),
+            line_logic: self.line_logic.clone(),
+        }
+    }
+
+    /// Runs line logic until the puzzle is done, it gets stuck, or it runs into a line that can't
+    /// fit its hint
+    pub fn solve(&mut self, file: &mut Option<&mut File>) -> Outcome {
+        loop {
+            self.stats.passes += 1;
+            let mut cells = self.game.grid.concat();
+            let (game, stats, logic) = (&self.game, &mut self.stats, &mut self.line_logic);
+            let swept = self.topology.sweep(
+                &mut cells,
+                &mut self.solved,
+                |line, hint| game.refine(line, hint, logic),
+                |i, line| {
+                    stats.lines_refined += 1;
+                    Self::log(game, file, game.line_job(i), line);
+                },
+            );
+            for (row, squares) in zip(&mut self.game.grid, cells.chunks(self.game.cols)) {
+                row.clone_from_slice(squares);
+            }
+            let Some(mut puzzle_changed) = swept else {
+                return Outcome::Contradiction;
+            };
+
+            let Some(megas_changed) = self.solve_megas(file) else {
+                return Outcome::Contradiction;
+            };
+            puzzle_changed |= megas_changed;
+
+            if self.solved.iter().all(|solved| *solved) {
+                return Outcome::Solved;
             }
             if !puzzle_changed {
-                panic!("Can't be solved completely");
+                return Outcome::Stuck;
+            }
+        }
+    }
+
+    /// Joint logic on the pairs of lines under mega clues. Their own hints are missing so
+    /// solved picks them up on the next pass. None on a contradiction,
+    /// otherwise whether anything changed
+    fn solve_megas(&mut self, file: &mut Option<&mut File>) -> Option<bool> {
+        let megas: Vec<(Job, MegaClue)> = self
+            .game
+            .megas()
+            .map(|(job, mega)| (job, mega.clone()))
+            .collect();
+        let mut changed = false;
+        for (job, mega) in megas {
+            let (first, second) = self.game.get_pair(job);
+            let (new_first, new_second, _, pair_changed) =
+                Game::refine_pair(&first, &second, &mega.groups)?;
+            if !pair_changed {
+                continue;
+            }
+            changed = true;
+            self.stats.lines_refined += 1;
+            for (job, line) in [(job, new_first), (job.next(), new_second)] {
+                Self::log(&self.game, file, job, &line);
+                self.game.set_line(job, line);
+            }
+        }
+        Some(changed)
+    }
+
+    /// Writes a refined line to the solve log, if there is one
+    fn log(game: &Game, file: &mut Option<&mut File>, job: Job, line: &[Square]) {
+        let Some(f) = file.as_mut() else {
+            return;
+        };
+        let (i, kind) = match job {
+            Job::Row(i) => (i, "row"),
+            Job::Col(i) => (i, "col"),
+        };
+        let squares: Vec<String> = line
+            .iter()
+            .map(|square| game.symbol(square).to_string())
+            .collect();
+        writeln!(f, "{} {} {}", i, kind, squares.join(" ")).unwrap();
+    }
+
+    /// Line logic plus guessing. Whenever line logic gets stuck it picks an unknown square, tries
+    /// every color and empty on it and recurses. Returns the solutions found, stopping once there are limit of
+    /// them, so a limit of 2 is enough to tell whether a puzzle is unique
+    pub fn search(&mut self, limit: usize) -> Vec<Grid> {
+        let mut solutions = Vec::new();
+        self.search_into(limit, &mut solutions);
+        solutions
+    }
+
+    fn search_into(&mut self, limit: usize, solutions: &mut Vec<Grid>) {
+        match self.solve(&mut None) {
+            Outcome::Contradiction => {}
+            Outcome::Solved => solutions.push(self.game.grid.clone()),
+            Outcome::Stuck => {
+                let (row, col) = self.pick_guess();
+                for guess in self.game.possible_squares() {
+                    let mut branch = self.branch();
+                    branch.game.grid[row][col] = guess;
+                    branch.search_into(limit, solutions);
+                    self.stats.guesses += 1;
+                    self.stats.absorb(&branch.stats);
+                    self.stats.max_depth = self.stats.max_depth.max(branch.stats.max_depth + 1);
+                    if solutions.len() >= limit {
+                        return;
+                    }
+                }
             }
         }
     }
+
+    /// Line logic, and when that gets stuck, probing: try each unknown square every way it could
+    /// be and run line logic on it. If all but one way end in a contradiction the square has to be
+    /// that one.
+    /// Keeps going until solved or a whole round of probing turns up nothing
+    pub fn probe(&mut self) -> Outcome {
+        loop {
+            let outcome = self.solve(&mut None);
+            if outcome != Outcome::Stuck {
+                return outcome;
+            }
+            let mut found = false;
+            for row in 0..self.game.rows {
+                for col in 0..self.game.cols {
+                    if self.game.grid[row][col] != Square::Unknown {
+                        continue;
+                    }
+                    let possible: Vec<Square> = self
+                        .game
+                        .possible_squares()
+                        .into_iter()
+                        .filter(|guess| {
+                            let mut branch = self.branch();
+                            branch.game.grid[row][col] = guess.clone();
+                            // Trial runs don't count towards the stats, only what they find out
+                            // does
+                            branch.solve(&mut None) != Outcome::Contradiction
+                        })
+                        .collect();
+                    match &possible[..] {
+                        [] => return Outcome::Contradiction,
+                        [only] => self.game.grid[row][col] = only.clone(),
+                        _ => continue,
+                    }
+                    self.stats.probes += 1;
+                    found = true;
+                }
+            }
+            if !found {
+                return Outcome::Stuck;
+            }
+            // Probed squares can be in lines that were already marked solved, which is fine,
+            // but anything else touching them needs another look
+            self.solved.fill(false);
+        }
+    }
+
+    /// Picks the unknown square to guess on. Going for the one whose row and col have the fewest
+    /// unknowns between them means a guess is more likely to set off more line logic
+    fn pick_guess(&self) -> (usize, usize) {
+        let grid = &self.game.grid;
+        let row_unknowns: Vec<usize> = grid
+            .iter()
+            .map(|row| row.iter().filter(|s| **s == Square::Unknown).count())
+            .collect();
+        let col_unknowns: Vec<usize> = (0..self.game.cols)
+            .map(|i| grid.iter().filter(|row| row[i] == Square::Unknown).count())
+            .collect();
+        (0..self.game.rows)
+            .flat_map(|row| (0..self.game.cols).map(move |col| (row, col)))
+            .filter(|(row, col)| grid[*row][*col] == Square::Unknown)
+            .min_by_key(|(row, col)| row_unknowns[*row] + col_unknowns[*col])
+            .expect("a stuck puzzle has unknown squares")
+    }
+}
+
+/// Characters used for each kind of square when printing a game as plain text
+pub struct Glyphs {
+    pub unknown: char,
+    pub filled: char,
+    pub empty: char,
+}
+
+impl Default for Glyphs {
+    fn default() -> Self {
+        Glyphs {
+            unknown: '.',
+            filled: '#',
+            empty: 'x',
+        }
+    }
+}
+
+impl Glyphs {
+    pub fn get(&self, square: &Square) -> char {
+        match square {
+            Square::Unknown => self.unknown,
+            Square::Filled(_) => self.filled,
+            Square::Triangle(corner) => corner.glyph(),
+            Square::Empty => self.empty,
+        }
+    }
+}
+
+impl Game {
+    /// Prints the clues and grid as plain text with no styling, so it's safe for logs, files and
+    /// tests. Col hints are stacked above the grid and row hints go to the left of it. Every
+    /// column is as wide as the widest col hint segment so multi digit clues line up.
+    /// EX:
+    ///       1
+    ///     1 1 2
+    ///   2 # # x
+    /// 1 1 # x #
+    pub fn to_text(&self, glyphs: &Glyphs) -> String {
+        self.to_text_marked(glyphs, &[])
+    }
+
+    /// Same as to_text, but the squares at marked come out as ? whatever they are. Used to point
+    /// out squares the hints don't pin down.
+    /// Squares filled with a color other than black use the palette's symbol instead of glyphs
+    pub fn to_text_marked(&self, glyphs: &Glyphs, marked: &[(usize, usize)]) -> String {
+        let col_labels = self.hint_labels(true);
+        let width = col_labels
+            .iter()
+            .flatten()
+            .map(|label| label.chars().count())
+            .max()
+            .unwrap_or(1);
+        let row_hint_strs: Vec<String> = self
+            .hint_labels(false)
+            .iter()
+            .map(|labels| labels.join(" "))
+            .collect();
+        let left = row_hint_strs
+            .iter()
+            .map(|s| s.chars().count())
+            .max()
+            .unwrap_or(0);
+        let max_col_hints = col_labels
+            .iter()
+            .map(|labels| labels.len())
+            .max()
+            .unwrap_or(0);
+
+        let mut lines = Vec::with_capacity(max_col_hints + self.rows);
+        for k in 0..max_col_hints {
+            // Hints are bottom aligned so the last segment always sits right on the grid
+            let cells: Vec<String> = col_labels
+                .iter()
+                .map(
+                    |labels| match (k + labels.len()).checked_sub(max_col_hints) {
+                        Some(seg) => format!("{:>width$}", labels[seg]),
+                        None => " ".repeat(width),
+                    },
+                )
+                .collect();
+            lines.push(format!("{:left$} {}", "", cells.join(" ")));
+        }
+        // Marks wrapped puzzles in the corner, which is always blank on the first hint line
+        if self.wrap && max_col_hints > 0 {
+            lines[0].replace_range(..1, "↻");
+        }
+        for (i, (row, hint_str)) in zip(&self.grid, &row_hint_strs).enumerate() {
+            let cells: Vec<String> = row
+                .iter()
+                .enumerate()
+                .map(|(j, square)| match (marked.contains(&(i, j)), square) {
+                    (true, _) => format!("{:>width$}", '?'),
+                    (false, Square::Filled(color)) if *color != 0 => {
+                        format!("{:>width$}", self.symbol(square))
+                    }
+                    (false, _) => format!("{:>width$}", glyphs.get(square)),
+                })
+                .collect();
+            lines.push(format!("{:>left$} {}", hint_str, cells.join(" ")));
+        }
+        lines
+            .iter()
+            .map(|line| line.trim_end())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
 }
 
 impl Display for Game {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let cols = self.cols;
-        let rows = self.rows;
-        let max_col_hints = self.col_hints.iter().map(|hint| hint.len()).max().unwrap();
-        let max_row_hints = self.row_hints.iter().map(|hint| hint.len()).max().unwrap();
-        let mut buffer = vec![vec![" "; cols + max_row_hints]; rows + max_col_hints];
-        Ok(())
+        f.write_str(&self.to_text(&Glyphs::default()))
     }
 }
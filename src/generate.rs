@@ -0,0 +1,130 @@
+use crate::{
+    difficulty::Tier,
+    game::{Game, Grid, Solver, Square},
+};
+
+/// SplitMix64. Hand rolled instead of pulling in rand so a seed gives the same puzzle forever,
+/// no matter what version of anything gets built against
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in [0, 1)
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniform in [0, n)
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+pub struct GeneratorOptions {
+    pub rows: usize,
+    pub cols: usize,
+    /// Chance of each square starting out filled
+    pub density: f64,
+    pub seed: u64,
+    /// Accept puzzles that need guessing to solve, as long as they're still unique. Otherwise
+    /// they have to be solvable with line logic alone
+    pub search: bool,
+    /// How many squares to flip before giving up on a picture and starting a new one
+    pub max_mutations: usize,
+    /// How many pictures to try before giving up altogether, since some tiers can't turn up at
+    /// all on small grids
+    pub max_pictures: usize,
+    /// Only accept puzzles that need exactly this much to solve. Overrides search. Puzzles that
+    /// need backtracking are rare, so asking for those can take a good while
+    pub tier: Option<Tier>,
+}
+
+impl Default for GeneratorOptions {
+    fn default() -> Self {
+        GeneratorOptions {
+            rows: 10,
+            cols: 10,
+            density: 0.55,
+            seed: 0,
+            search: false,
+            max_mutations: 200,
+            max_pictures: 1000,
+            tier: None,
+        }
+    }
+}
+
+/// Cells that the puzzle doesn't pin down. For line logic that's whatever it leaves unknown,
+/// for search it's wherever the first 2 solutions disagree. Empty means the puzzle is unique
+pub fn ambiguous_cells(game: &Game, search: bool) -> Vec<(usize, usize)> {
+    let mut blank = game.clone();
+    blank.grid = vec![vec![Square::Unknown; game.cols]; game.rows];
+    let mut solver = Solver::new(blank);
+
+    let cells = (0..game.rows).flat_map(|row| (0..game.cols).map(move |col| (row, col)));
+    match search {
+        true => match &solver.search(2)[..] {
+            [first, second] => cells
+                .filter(|(row, col)| first[*row][*col] != second[*row][*col])
+                .collect(),
+            _ => vec![],
+        },
+        false => {
+            solver.solve(&mut None);
+            let grid = &solver.game.grid;
+            cells
+                .filter(|(row, col)| grid[*row][*col] == Square::Unknown)
+                .collect()
+        }
+    }
+}
+
+/// Makes a random puzzle with exactly one solution. Starts from random noise and keeps flipping
+/// squares the clues don't pin down until they do. Same options always give the same puzzle.
+/// The picture ends up as the game's solution. With a tier, unique puzzles from the wrong tier
+/// get thrown out and it tries again with a new picture. None if max_pictures go by without one
+pub fn generate(options: &GeneratorOptions) -> Option<Game> {
+    // Anything past overlap needs search to tell whether it's unique at all
+    let search = options.search || options.tier.is_some_and(|tier| tier > Tier::Overlap);
+    let mut rng = Rng::new(options.seed);
+    for _ in 0..options.max_pictures {
+        let mut picture: Grid = (0..options.rows)
+            .map(|_| {
+                (0..options.cols)
+                    .map(|_| match rng.next_f64() < options.density {
+                        true => Square::Filled(0),
+                        false => Square::Empty,
+                    })
+                    .collect()
+            })
+            .collect();
+
+        for _ in 0..options.max_mutations {
+            let game = Game::from_solution(picture.clone());
+            let ambiguous = ambiguous_cells(&game, search);
+            if ambiguous.is_empty() {
+                match options.tier {
+                    Some(tier) if game.tier() != Some(tier) => break,
+                    _ => return Some(game),
+                }
+            }
+            let (row, col) = ambiguous[rng.below(ambiguous.len())];
+            picture[row][col] = match picture[row][col] {
+                Square::Filled(_) => Square::Empty,
+                _ => Square::Filled(0),
+            };
+        }
+    }
+    None
+}
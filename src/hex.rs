@@ -0,0 +1,264 @@
+use std::ops::RangeInclusive;
+
+use anyhow::{bail, Result};
+
+use crate::{
+    game::{Game, Glyphs, Hint, Outcome, PaletteColor, Segment, Square},
+    parse::{parse_hint, ParseError},
+    topology::{Line, Topology},
+};
+
+/// Hex nonogram on a board shaped like a big hexagon, made of pointy topped cells in rows that
+/// shift half a cell each. Every cell is on 3 lines: its row, a line running down to the right
+/// and one running down to the left. Black and white only.
+/// Cells are found by axial coords (q, r): r is the row, -radius at the top, and q goes up by 1
+/// a cell to the right. Lines running down to the right keep q the same, lines running down to
+/// the left keep q + r the same
+#[derive(Clone, Debug)]
+pub struct Hex {
+    /// Cells from the middle to the edge, not counting the middle one. The board is
+    /// 2 * radius + 1 cells across
+    pub radius: usize,
+    /// Every cell, row by row from the top, left to right. See Hex::index
+    pub cells: Vec<Square>,
+    /// Hints for the rows from the top, then the lines running down to the right, then down to
+    /// the left, each of those from left to right
+    pub hints: [Vec<Hint>; 3],
+}
+
+impl Hex {
+    /// Board with every cell unknown. Needs 2 * radius + 1 hints for each direction
+    pub fn new(radius: usize, hints: [Vec<Hint>; 3]) -> Result<Hex> {
+        let across = 2 * radius + 1;
+        if hints.iter().any(|hints| hints.len() != across) {
+            bail!("a hex of radius {} needs {} hints each way", radius, across);
+        }
+        let plain = hints
+            .iter()
+            .flatten()
+            .flatten()
+            .all(|seg| seg.color == 0 && seg.start.is_none() && seg.end.is_none());
+        if !plain {
+            bail!("hex puzzles are black and white");
+        }
+        let mut hex = Hex {
+            radius,
+            cells: vec![],
+            hints,
+        };
+        let size = hex.rows().map(|r| hex.row_range(r).count()).sum();
+        hex.cells = vec![Square::Unknown; size];
+        if let Some(line) = hex
+            .topology()
+            .lines
+            .iter()
+            .find(|line| Segment::min_len(&line.hint) > line.cells.len())
+        {
+            bail!(
+                "a line of {} cells can't fit {}",
+                line.cells.len(),
+                Segment::min_len(&line.hint)
+            );
+        }
+        Ok(hex)
+    }
+
+    fn rows(&self) -> RangeInclusive<isize> {
+        let radius = self.radius as isize;
+        -radius..=radius
+    }
+
+    /// q of every cell in row r
+    fn row_range(&self, r: isize) -> RangeInclusive<isize> {
+        let radius = self.radius as isize;
+        (-radius).max(-radius - r)..=radius.min(radius - r)
+    }
+
+    /// Index into cells of (q, r)
+    pub fn index(&self, q: isize, r: isize) -> usize {
+        let above: usize = self
+            .rows()
+            .take_while(|row| *row < r)
+            .map(|row| self.row_range(row).count())
+            .sum();
+        above + (q - self.row_range(r).start()) as usize
+    }
+
+    /// Every line on the board with its hint, in the same order as hints. Rows go left to right
+    /// and the other lines top to bottom
+    pub fn topology(&self) -> Topology {
+        let rows = self.rows().map(|r| {
+            self.row_range(r)
+                .map(|q| self.index(q, r))
+                .collect::<Vec<_>>()
+        });
+        // Keeping q, the rows it crosses are the ones where q is in range
+        let down_right = self.rows().map(|q| {
+            self.rows()
+                .filter(|r| self.row_range(*r).contains(&q))
+                .map(|r| self.index(q, r))
+                .collect::<Vec<_>>()
+        });
+        // Keeping q + r, left to right means it starts out low
+        let down_left = self.rows().map(|sum| {
+            self.rows()
+                .filter(|r| self.row_range(*r).contains(&(sum - r)))
+                .map(|r| self.index(sum - r, r))
+                .collect::<Vec<_>>()
+        });
+        let lines = rows
+            .chain(down_right)
+            .chain(down_left)
+            .zip(self.hints.iter().flatten())
+            .map(|(cells, hint)| Line::new(cells, hint.clone()))
+            .collect();
+        Topology { lines }
+    }
+
+    /// Runs line logic over all 3 directions
+    pub fn solve(&mut self) -> Outcome {
+        self.topology().propagate(&mut self.cells, |line, hint| {
+            match Segment::is_missing(hint) {
+                // Nothing to go on without a clue, the lines crossing it have to do it all
+                true => Some((line.to_vec(), !line.contains(&Square::Unknown), false)),
+                false => Game::refine_line(line, hint),
+            }
+        })
+    }
+
+    /// The board as plain text with the row hints down the left and the hints for the other 2
+    /// directions underneath, left to right with | between lines.
+    /// EX:
+    ///   2  # #
+    /// 1 1 # x #
+    ///   1  x #
+    /// ↘ 1 | 1 1 | 2
+    /// ↙ 2 | 1 | 2
+    pub fn to_text(&self, glyphs: &Glyphs) -> String {
+        self.draw(|square| glyphs.get(square).to_string())
+    }
+
+    /// Same as to_text, with the squares styled for the terminal
+    pub fn render(&self) -> String {
+        self.draw(|square| square.to_string())
+    }
+
+    fn draw(&self, cell: impl Fn(&Square) -> String) -> String {
+        let label = |hint: &Hint| {
+            hint.iter()
+                .map(|seg| seg.to_string())
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        let row_labels: Vec<String> = self.hints[0].iter().map(label).collect();
+        let left = row_labels
+            .iter()
+            .map(|label| label.chars().count())
+            .max()
+            .unwrap_or(0);
+
+        let mut lines = vec![];
+        for (r, row_label) in self.rows().zip(&row_labels) {
+            // Each row shifts half a cell, which is a character
+            let cells: Vec<String> = self
+                .row_range(r)
+                .map(|q| cell(&self.cells[self.index(q, r)]))
+                .collect();
+            lines.push(format!(
+                "{:>left$} {}{}",
+                row_label,
+                " ".repeat(r.unsigned_abs()),
+                cells.join(" ")
+            ));
+        }
+        for (arrow, hints) in ["↘", "↙"].iter().zip(&self.hints[1..]) {
+            let labels: Vec<String> = hints.iter().map(label).collect();
+            lines.push(format!("{} {}", arrow, labels.join(" | ")));
+        }
+        lines
+            .iter()
+            .map(|line| line.trim_end())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Hex puzzle files start with hex and the radius, then the hints for the rows, the lines
+/// running down to the right, and the ones running down to the left, with a blank line between
+/// each lot. Hints are written the same as in normal puzzle files, black only.
+/// EX:
+/// hex 1
+/// 2
+/// 1 1
+/// 1
+///
+/// 1
+/// 1 1
+/// 1
+///
+/// 1
+/// 1 1
+/// 1
+impl Hex {
+    pub fn from_hints_str(s: &str) -> Result<Hex, ParseError> {
+        let lines: Vec<_> = s.lines().map(|line| line.trim()).collect();
+        let start = lines
+            .iter()
+            .position(|line| !line.is_empty())
+            .ok_or(ParseError::Empty)?;
+        let bad_hex = |line, problem| ParseError::BadLayout { line, problem };
+        let radius: usize = lines[start]
+            .strip_prefix("hex")
+            .and_then(|radius| radius.trim().parse().ok())
+            .ok_or(bad_hex(start + 1, "should start with hex and its radius"))?;
+
+        // Lots of hints are split up by blank lines, however many
+        let mut lots: Vec<Vec<(usize, &str)>> = vec![];
+        let mut after_blank = true;
+        for (i, line) in lines.iter().enumerate().skip(start + 1) {
+            if line.is_empty() {
+                after_blank = true;
+                continue;
+            }
+            if after_blank {
+                lots.push(vec![]);
+                after_blank = false;
+            }
+            lots.last_mut().expect("just pushed").push((i + 1, *line));
+        }
+        let across = 2 * radius + 1;
+        if lots.len() != 3 || lots.iter().any(|lot| lot.len() != across) {
+            return Err(bad_hex(
+                lines.len(),
+                "needs 3 lots of hints, 2 * radius + 1 each",
+            ));
+        }
+
+        let palette = [PaletteColor::black()];
+        let parse_lot = |lot: &Vec<(usize, &str)>| {
+            lot.iter()
+                .map(|(line_num, line)| {
+                    let hint = parse_hint(*line_num, line, &palette, false)?;
+                    match hint
+                        .iter()
+                        .all(|seg| seg.start.is_none() && seg.end.is_none())
+                    {
+                        true => Ok(hint),
+                        false => Err(ParseError::BadSegment {
+                            line: *line_num,
+                            segment: line.to_string(),
+                        }),
+                    }
+                })
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let hints = [
+            parse_lot(&lots[0])?,
+            parse_lot(&lots[1])?,
+            parse_lot(&lots[2])?,
+        ];
+        // Colors and triangles were checked above, so that only leaves hints too long for their
+        // lines
+        Hex::new(radius, hints).map_err(|_| bad_hex(lines.len(), "hint is too long for its line"))
+    }
+}
@@ -0,0 +1,277 @@
+use thiserror::Error;
+
+use crate::game::{Game, Square};
+
+/// A greyscale image, 0 is black and 255 is white
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    /// Row by row
+    pub pixels: Vec<u8>,
+}
+
+#[derive(Debug, Error)]
+pub enum ImportError {
+    #[error("not a PBM, PGM or PNG file")]
+    UnknownFormat,
+    #[error("bad PNM header")]
+    BadHeader,
+    #[error("image data is cut short")]
+    Truncated,
+    #[error("image has no pixels")]
+    Empty,
+    #[error("png: {0}")]
+    Png(#[from] png::DecodingError),
+}
+
+/// Reads the whitespace separated header fields of a PNM file, skipping # comments.
+/// Returns the fields and the index of the byte just after the single whitespace that ends them
+fn pnm_header(bytes: &[u8], fields: usize) -> Result<(Vec<usize>, usize), ImportError> {
+    let mut values = Vec::with_capacity(fields);
+    // Skip the 2 byte magic
+    let mut i = 2;
+    while values.len() < fields {
+        match bytes.get(i) {
+            None => return Err(ImportError::BadHeader),
+            Some(b'#') => {
+                while bytes.get(i).is_some_and(|b| *b != b'\n') {
+                    i += 1;
+                }
+            }
+            Some(b) if b.is_ascii_whitespace() => i += 1,
+            Some(b) if b.is_ascii_digit() => {
+                let start = i;
+                while bytes.get(i).is_some_and(|b| b.is_ascii_digit()) {
+                    i += 1;
+                }
+                let field = std::str::from_utf8(&bytes[start..i]).expect("digits are utf8");
+                values.push(field.parse().map_err(|_| ImportError::BadHeader)?);
+            }
+            Some(_) => return Err(ImportError::BadHeader),
+        }
+    }
+    Ok((values, i + 1))
+}
+
+/// Ascii PBM pixels are each a single 0 or 1, and don't need any whitespace between them
+fn ascii_bits(bytes: &[u8]) -> Vec<usize> {
+    String::from_utf8_lossy(bytes)
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or(""))
+        .flat_map(|line| {
+            line.chars()
+                .filter_map(|c| c.to_digit(2))
+                .collect::<Vec<_>>()
+        })
+        .map(|bit| bit as usize)
+        .collect()
+}
+
+/// Ascii PGM is just numbers separated by whitespace
+fn ascii_values(bytes: &[u8]) -> Vec<usize> {
+    String::from_utf8_lossy(bytes)
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or(""))
+        .flat_map(|line| {
+            line.split_whitespace()
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        })
+        .filter_map(|value| value.parse().ok())
+        .collect()
+}
+
+impl Image {
+    /// Loads any of the supported formats, going by the magic bytes at the start
+    pub fn decode(bytes: &[u8]) -> Result<Image, ImportError> {
+        let image = match bytes {
+            [0x89, b'P', b'N', b'G', ..] => Image::decode_png(bytes),
+            [b'P', b'1' | b'2' | b'4' | b'5', ..] => Image::decode_pnm(bytes),
+            _ => Err(ImportError::UnknownFormat),
+        }?;
+        // Nothing to scale down from, same as a puzzle file with no rows or cols
+        if image.width == 0 || image.height == 0 {
+            return Err(ImportError::Empty);
+        }
+        Ok(image)
+    }
+
+    /// PBM (P1/P4) and PGM (P2/P5)
+    pub fn decode_pnm(bytes: &[u8]) -> Result<Image, ImportError> {
+        let kind = bytes.get(1).copied().ok_or(ImportError::BadHeader)?;
+        let bitmap = matches!(kind, b'1' | b'4');
+        let (header, data_start) = pnm_header(bytes, if bitmap { 2 } else { 3 })?;
+        let (width, height) = (header[0], header[1]);
+        let max = if bitmap { 1 } else { header[2].max(1) };
+        let data = bytes.get(data_start..).unwrap_or(&[]);
+        // Sizes come straight from the file, so they could be anything
+        let count = width.checked_mul(height).ok_or(ImportError::BadHeader)?;
+
+        let values: Vec<usize> = match kind {
+            // Packed bits, 1 is black, every row starts on a fresh byte
+            b'4' => {
+                let row_bytes = width.div_ceil(8);
+                let needed = row_bytes
+                    .checked_mul(height)
+                    .ok_or(ImportError::BadHeader)?;
+                if data.len() < needed {
+                    return Err(ImportError::Truncated);
+                }
+                (0..height)
+                    .flat_map(|y| {
+                        (0..width).map(move |x| (data[y * row_bytes + x / 8] >> (7 - x % 8)) & 1)
+                    })
+                    .map(usize::from)
+                    .collect()
+            }
+            b'5' if max > 255 => data
+                .chunks_exact(2)
+                .map(|pair| usize::from(u16::from_be_bytes([pair[0], pair[1]])))
+                .collect(),
+            b'5' => data.iter().map(|b| usize::from(*b)).collect(),
+            b'1' => ascii_bits(data),
+            _ => ascii_values(data),
+        };
+        if values.len() < count {
+            return Err(ImportError::Truncated);
+        }
+        let pixels = values[..count]
+            .iter()
+            .map(|value| match bitmap {
+                true if *value == 1 => 0,
+                true => 255,
+                false => (value.min(&max) * 255 / max) as u8,
+            })
+            .collect();
+        Ok(Image {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    pub fn decode_png(bytes: &[u8]) -> Result<Image, ImportError> {
+        let mut decoder = png::Decoder::new(bytes);
+        // Get everything down to 8 bit channels so there's only a handful of layouts to handle
+        decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+        let mut reader = decoder.read_info()?;
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf)?;
+        buf.truncate(info.buffer_size());
+        let (width, height) = (info.width as usize, info.height as usize);
+
+        let channels = info.color_type.samples();
+        let pixels = buf
+            .chunks_exact(channels)
+            .map(|px| {
+                let (luma, alpha) = match px {
+                    [grey] => (*grey as u32, 255),
+                    [grey, alpha] => (*grey as u32, *alpha as u32),
+                    [r, g, b] => (Image::luma(*r, *g, *b), 255),
+                    [r, g, b, alpha, ..] => (Image::luma(*r, *g, *b), *alpha as u32),
+                    [] => (255, 255),
+                };
+                // See through pixels count as background, so blend towards white
+                ((luma * alpha + 255 * (255 - alpha)) / 255) as u8
+            })
+            .collect();
+        Ok(Image {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    fn luma(r: u8, g: u8, b: u8) -> u32 {
+        (299 * r as u32 + 587 * g as u32 + 114 * b as u32) / 1000
+    }
+
+    /// Scales to width x height. Shrinking averages each block of pixels, growing just repeats
+    /// the nearest one
+    pub fn resize(&self, width: usize, height: usize) -> Image {
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            let (y0, y1) = (y * self.height / height, ((y + 1) * self.height / height));
+            let y1 = y1.max(y0 + 1).min(self.height);
+            for x in 0..width {
+                let (x0, x1) = (x * self.width / width, ((x + 1) * self.width / width));
+                let x1 = x1.max(x0 + 1).min(self.width);
+                let mut total = 0usize;
+                for row in y0..y1 {
+                    total += self.pixels[row * self.width + x0..row * self.width + x1]
+                        .iter()
+                        .map(|p| *p as usize)
+                        .sum::<usize>();
+                }
+                pixels.push((total / ((y1 - y0) * (x1 - x0))) as u8);
+            }
+        }
+        Image {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Anything darker than level gets filled
+    pub fn threshold(&self, level: u8) -> Vec<Vec<Square>> {
+        self.pixels
+            .chunks(self.width.max(1))
+            .map(|row| {
+                row.iter()
+                    .map(|p| match *p < level {
+                        true => Square::Filled(0),
+                        false => Square::Empty,
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Floyd-Steinberg dithering, for images with lots of in between greys where a hard threshold
+    /// would lose the shading
+    pub fn dither(&self) -> Vec<Vec<Square>> {
+        let (w, h) = (self.width, self.height);
+        let mut values: Vec<f32> = self.pixels.iter().map(|p| *p as f32).collect();
+        let mut grid = vec![vec![Square::Empty; w]; h];
+        for y in 0..h {
+            for x in 0..w {
+                let old = values[y * w + x];
+                let new = if old < 128.0 { 0.0 } else { 255.0 };
+                if new == 0.0 {
+                    grid[y][x] = Square::Filled(0);
+                }
+                let error = old - new;
+                let mut spread = |dx: isize, dy: usize, weight: f32| {
+                    let nx = x as isize + dx;
+                    if nx >= 0 && (nx as usize) < w && y + dy < h {
+                        values[(y + dy) * w + nx as usize] += error * weight;
+                    }
+                };
+                spread(1, 0, 7.0 / 16.0);
+                spread(-1, 1, 3.0 / 16.0);
+                spread(0, 1, 5.0 / 16.0);
+                spread(1, 1, 1.0 / 16.0);
+            }
+        }
+        grid
+    }
+}
+
+/// How to turn greys into filled or empty
+pub enum Binarize {
+    Threshold(u8),
+    Dither,
+}
+
+impl Game {
+    /// Turns an image into a puzzle of the given size, with the picture as its solution
+    pub fn from_image(image: &Image, cols: usize, rows: usize, binarize: Binarize) -> Game {
+        let image = image.resize(cols, rows);
+        let grid = match binarize {
+            Binarize::Threshold(level) => image.threshold(level),
+            Binarize::Dither => image.dither(),
+        };
+        Game::from_solution(grid)
+    }
+}
@@ -0,0 +1,210 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use crate::game::{Game, Hint, LineLogic, Refined, Segment, Square};
+
+/// A single line technique. Takes a line, as much of it known as is, and its hint, and gives
+/// back the refined line, whether it's solved and whether it changed. None when the hint can't
+/// fit. Missing clues and wrapping are sorted out by Game::refine before it gets here, so
+/// solvers only ever see a plain straight line.
+/// Takes &mut self so solvers can keep whatever they like between lines
+pub trait LineSolver {
+    fn refine_line(&mut self, line: &[Square], hint: &[Segment]) -> Refined;
+}
+
+/// Overlap of the left-most and right-most placements. See Game::refine_line
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Overlap;
+
+impl LineSolver for Overlap {
+    fn refine_line(&mut self, line: &[Square], hint: &[Segment]) -> Refined {
+        Game::refine_line(line, hint)
+    }
+}
+
+/// Everything the line can tell on its own. See Game::refine_line_complete
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Complete;
+
+impl LineSolver for Complete {
+    fn refine_line(&mut self, line: &[Square], hint: &[Segment]) -> Refined {
+        Game::refine_line_complete(line, hint)
+    }
+}
+
+/// Tries every way of filling in the unknown squares and keeps what all the ones that fit the
+/// hint agree on. Finds the same as Complete, just far slower, so it's there to check the others
+/// against on small puzzles
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BruteForce;
+
+impl BruteForce {
+    /// Most fillings worth going through for one line, past this it takes too long
+    pub const MAX_FILLINGS: u64 = 1 << 20;
+
+    /// Anything that can go in a gap is empty, a color from the hint, or a triangle from the
+    /// hint, which only ever turn up at the ends of segments
+    fn options(hint: &[Segment]) -> Vec<Square> {
+        let mut options = vec![Square::Empty];
+        for seg in hint {
+            let corners = [seg.start, seg.end].into_iter().flatten();
+            for square in corners
+                .map(Square::Triangle)
+                .chain([Square::Filled(seg.color)])
+            {
+                if !options.contains(&square) {
+                    options.push(square);
+                }
+            }
+        }
+        options
+    }
+
+    /// Whether solving game from blank keeps every line under MAX_FILLINGS. That's options to
+    /// the power of unknowns, and on a blank line every square is unknown
+    pub fn fits(game: &Game) -> bool {
+        let rows = game.row_hints.iter().map(|hint| (hint, game.cols));
+        let cols = game.col_hints.iter().map(|hint| (hint, game.rows));
+        rows.chain(cols).all(|(hint, len)| {
+            (BruteForce::options(hint).len() as u64)
+                .checked_pow(len as u32)
+                .is_some_and(|fillings| fillings <= BruteForce::MAX_FILLINGS)
+        })
+    }
+}
+
+impl LineSolver for BruteForce {
+    fn refine_line(&mut self, line: &[Square], hint: &[Segment]) -> Refined {
+        let options = BruteForce::options(hint);
+        let unknowns: Vec<usize> = (0..line.len())
+            .filter(|i| line[*i] == Square::Unknown)
+            .collect();
+
+        // Counts through every filling like an odometer, one digit per unknown square
+        let mut picks = vec![0; unknowns.len()];
+        let mut filled = line.to_vec();
+        let mut agreed: Option<Vec<Square>> = None;
+        loop {
+            for (i, pick) in unknowns.iter().zip(&picks) {
+                filled[*i] = options[*pick].clone();
+            }
+            if Game::check_line(hint, &filled) {
+                agreed = Some(Game::agree(agreed, filled.clone()));
+            }
+            let Some(digit) = picks.iter().position(|pick| pick + 1 < options.len()) else {
+                break;
+            };
+            picks[digit] += 1;
+            picks[..digit].fill(0);
+        }
+
+        let new_line = agreed?;
+        let solved = !new_line.contains(&Square::Unknown);
+        let changed = new_line != line;
+        Some((new_line, solved, changed))
+    }
+}
+
+/// Picks between the built in techniques as it goes, so the solver can move up to a stronger
+/// one partway through
+impl LineSolver for LineLogic {
+    fn refine_line(&mut self, line: &[Square], hint: &[Segment]) -> Refined {
+        match self {
+            LineLogic::Overlap => Overlap.refine_line(line, hint),
+            LineLogic::Complete => Complete.refine_line(line, hint),
+        }
+    }
+}
+
+/// How a Cached line solver's been getting on
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Lines that were already in the cache
+    pub hits: usize,
+    /// Lines that had to go to the solver underneath
+    pub misses: usize,
+    /// Lines in the cache right now
+    pub len: usize,
+}
+
+#[derive(Debug, Default)]
+struct LineCache {
+    /// Keyed by hint first so lookups can go by a borrowed hint and line, and only inserting
+    /// needs to allocate
+    lines: HashMap<Hint, HashMap<Vec<u8>, Refined>>,
+    capacity: usize,
+    stats: CacheStats,
+}
+
+/// Remembers what inner made of every hint and line it's been given, since propagation and
+/// especially probing and backtracking keep going over the same lines. Clones share the one
+/// cache, so branches of a Solver all fill in and use the same one. Once it holds capacity
+/// lines it starts again from empty
+#[derive(Clone, Debug)]
+pub struct Cached<L> {
+    inner: L,
+    cache: Rc<RefCell<LineCache>>,
+}
+
+impl<L: LineSolver> Cached<L> {
+    /// Lines kept by Cached::new, which is plenty for big puzzles without using more than a few
+    /// tens of MB
+    pub const DEFAULT_CAPACITY: usize = 100_000;
+
+    pub fn new(inner: L) -> Self {
+        Cached::with_capacity(inner, Cached::<L>::DEFAULT_CAPACITY)
+    }
+
+    pub fn with_capacity(inner: L, capacity: usize) -> Self {
+        Cached {
+            inner,
+            cache: Rc::new(RefCell::new(LineCache {
+                capacity,
+                ..Default::default()
+            })),
+        }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.cache.borrow().stats
+    }
+}
+
+/// A byte per square: unknown, empty, the 4 triangles, then colors. None for a color too big
+/// to fit, which just doesn't get cached
+fn encode_line(line: &[Square]) -> Option<Vec<u8>> {
+    line.iter()
+        .map(|square| match square {
+            Square::Unknown => Some(0),
+            Square::Empty => Some(1),
+            Square::Triangle(corner) => Some(2 + *corner as u8),
+            Square::Filled(color) => color.checked_add(6),
+        })
+        .collect()
+}
+
+impl<L: LineSolver> LineSolver for Cached<L> {
+    fn refine_line(&mut self, line: &[Square], hint: &[Segment]) -> Refined {
+        let Some(key) = encode_line(line) else {
+            return self.inner.refine_line(line, hint);
+        };
+        let mut cache = self.cache.borrow_mut();
+        if let Some(refined) = cache.lines.get(hint).and_then(|lines| lines.get(&key)) {
+            let refined = refined.clone();
+            cache.stats.hits += 1;
+            return refined;
+        }
+        cache.stats.misses += 1;
+        let refined = self.inner.refine_line(line, hint);
+        if cache.stats.len >= cache.capacity {
+            cache.lines.clear();
+            cache.stats.len = 0;
+        }
+        cache.stats.len += 1;
+        cache
+            .lines
+            .entry(hint.to_vec())
+            .or_default()
+            .insert(key, refined.clone());
+        refined
+    }
+}
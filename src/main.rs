@@ -1,24 +1,45 @@
 use std::{
-    fs::File,
-    io::{self, Write},
-    time::Duration,
-    vec,
+    env,
+    fs::{self, File},
+    io::{self, IsTerminal, Write},
+    time::{Duration, Instant},
 };
 
-use crossterm::{
-    cursor::{self, MoveToNextLine},
-    execute, queue,
-    style::{self, Print, Stylize},
-    terminal,
-};
-use game::{Game, Square};
+use anyhow::{bail, Result};
+use game::Game;
 
+mod cube;
+mod difficulty;
+mod edit;
 mod game;
+mod generate;
+mod hex;
+mod import;
+mod line_solver;
+mod parse;
+mod play;
+mod raster;
 mod render;
+mod svg;
+#[cfg(test)]
 mod test;
-use crate::test::line::test_line;
+mod topology;
+
+const USAGE: &str = "usage:
+    nonagram solve [puzzle file]
+    nonagram play [puzzle file]
+    nonagram edit [puzzle file | --size=COLSxROWS] [--out=file]
+    nonagram svg [puzzle file] [--solution]
+    nonagram image [puzzle file] <output .pbm/.pgm/.png> [--scale=N]
+    nonagram import <image .pbm/.pgm/.png> [--size=COLSxROWS] [--threshold=N | --dither]
+    nonagram generate [--size=COLSxROWS] [--density=0.55] [--seed=N] [--search]
+        [--tier=overlap|line|probing|backtracking]
+    nonagram rate [puzzle files...]
+    nonagram compare [puzzle files...]
+    nonagram cube <cube puzzle file>
+    nonagram hex <hex puzzle file>";
 
-fn main() -> anyhow::Result<()> {
+fn sample_game() -> Game {
     // let col_hints = vec![
     //     vec![4],
     //     vec![2, 1],
@@ -64,51 +85,388 @@ fn main() -> anyhow::Result<()> {
         vec![1, 1, 1],
         vec![1, 1, 1],
     ];
+    Game::new(col_hints, row_hints).expect("sample puzzle is valid")
+}
+
+fn load_game(path: Option<&String>) -> Result<Game> {
+    match path {
+        None => Ok(sample_game()),
+        Some(path) => Ok(Game::from_hints_str(&fs::read_to_string(path)?)?),
+    }
+}
+
+/// Solves a 3D puzzle and prints it a slice at a time
+fn cube(path: &str) -> Result<()> {
+    let mut cube = cube::Cube::from_clues_str(&fs::read_to_string(path)?)?;
+    match cube.solve() {
+        game::Outcome::Solved => {}
+        game::Outcome::Stuck => eprintln!("line logic got stuck, blanks are what it couldn't get"),
+        game::Outcome::Contradiction => eprintln!("the clues contradict each other"),
+    }
+    match io::stdout().is_terminal() {
+        true => println!("{}", cube.render_slices()),
+        false => println!("{}", cube.to_text(&game::Glyphs::default())),
+    }
+    Ok(())
+}
+
+/// Solves a hex puzzle and prints the board
+fn hex(path: &str) -> Result<()> {
+    let mut hex = hex::Hex::from_hints_str(&fs::read_to_string(path)?)?;
+    match hex.solve() {
+        game::Outcome::Solved => {}
+        game::Outcome::Stuck => eprintln!("line logic got stuck, blanks are what it couldn't get"),
+        game::Outcome::Contradiction => eprintln!("the hints contradict each other"),
+    }
+    match io::stdout().is_terminal() {
+        true => println!("{}", hex.render()),
+        false => println!("{}", hex.to_text(&game::Glyphs::default())),
+    }
+    Ok(())
+}
+
+fn solve(game: Game) -> Result<()> {
     let mut file = File::create("solution.txt")?;
-    file.write_all(
-        row_hints
-            .clone()
-            .iter()
-            .map(|hint| {
-                hint.iter()
-                    .map(|seg| seg.to_string())
-                    .collect::<Vec<_>>()
-                    .join(" ")
-            })
-            .collect::<Vec<_>>()
-            .join("\n")
-            .as_bytes(),
-    )?;
-    file.write(b"\n\n");
-    file.write_all(
-        col_hints
-            .clone()
+    file.write_all(game.hints_to_string().as_bytes())?;
+    file.write_all(b"\n")?;
+    let mut solver = game::Solver::new(game);
+
+    // Point out where the clues fall short when line logic can't finish
+    let ambiguous = match solver.solve(&mut Some(&mut file)) {
+        game::Outcome::Solved => vec![],
+        game::Outcome::Stuck => {
+            let ambiguous = generate::ambiguous_cells(&solver.game, true);
+            // Nothing ambiguous means there's only the one solution, line logic just can't get
+            // there on its own, so guess the rest
+            if ambiguous.is_empty() {
+                let Some(grid) = solver.search(1).pop() else {
+                    bail!("the clues contradict each other, there's no solution");
+                };
+                eprintln!("line logic got stuck, the rest took guessing");
+                solver.game.grid = grid;
+            }
+            ambiguous
+        }
+        // Whatever got worked out before it hit the contradiction breaks the clues somewhere, so
+        // there's nothing worth printing
+        game::Outcome::Contradiction => {
+            bail!("the clues contradict each other, there's no solution")
+        }
+    };
+    if !ambiguous.is_empty() {
+        eprintln!(
+            "{} square(s) the clues don't pin down, marked with ?",
+            ambiguous.len()
+        );
+    }
+
+    // Colors are just noise when piped somewhere, so stick to plain text there
+    match io::stdout().is_terminal() {
+        true => {
+            let highlight = render::Highlight {
+                ambiguous,
+                ..Default::default()
+            };
+            println!(
+                "{}",
+                render::double_vec_to_string(solver.game.render_all(&highlight))
+            )
+        }
+        false => println!(
+            "{}",
+            solver
+                .game
+                .to_text_marked(&game::Glyphs::default(), &ambiguous)
+        ),
+    }
+    Ok(())
+}
+
+/// Prints the puzzle as SVG to stdout, along with its solution if asked for. If line logic can't
+/// get to the solution, it draws as far as it got and shades the squares the clues don't pin down
+fn svg(mut game: Game, with_solution: bool) -> Result<()> {
+    let mut ambiguous = vec![];
+    if with_solution {
+        match game.find_solution() {
+            true => game.grid = game.solution.clone().expect("just found it"),
+            false => {
+                ambiguous = generate::ambiguous_cells(&game, true);
+                eprintln!(
+                    "couldn't solve the puzzle, {} square(s) the clues don't pin down are shaded",
+                    ambiguous.len()
+                );
+                let mut solver = game::Solver::new(game);
+                solver.solve(&mut None);
+                game = solver.game;
+            }
+        }
+    }
+    print!("{}", game.to_svg_marked(with_solution, &ambiguous));
+    Ok(())
+}
+
+/// Solves as far as line logic gets and writes the grid out as an image. Format goes by the
+/// output's extension. Anything left unsolved shows up grey (or white in PBM)
+fn image(game: Game, out: &str, scale: usize) -> Result<()> {
+    let mut solver = game::Solver::new(game);
+    if solver.solve(&mut None) != game::Outcome::Solved {
+        eprintln!("couldn't solve the whole puzzle, writing what was worked out");
+    }
+    let game = solver.game;
+    let bytes = match out.rsplit_once('.').map(|(_, ext)| ext) {
+        Some("pbm") => game.to_pbm(scale),
+        Some("pgm") => game.to_pgm(scale),
+        Some("png") => game.to_png(scale)?,
+        _ => bail!(
+            "don't know what image format {} is, try .pbm, .pgm or .png",
+            out
+        ),
+    };
+    fs::write(out, bytes)?;
+    Ok(())
+}
+
+/// Turns a picture into a puzzle file on stdout, and says on stderr whether it can be solved
+fn import(path: &str, size: Option<&str>, binarize: import::Binarize) -> Result<()> {
+    let image = import::Image::decode(&fs::read(path)?)?;
+    let (cols, rows) = match size {
+        None => (image.width, image.height),
+        Some(size) => parse_size(size)?,
+    };
+    let game = Game::from_image(&image, cols, rows, binarize);
+    // Searching is slow, so only when line logic can't settle it
+    match game.line_solve() {
+        Some(_) => eprintln!("uniquely solvable with line logic"),
+        None => match generate::ambiguous_cells(&game, true).len() {
+            0 => eprintln!("unique, but line logic gets stuck and it takes guessing"),
+            n => eprintln!("not unique, {} square(s) the clues don't pin down", n),
+        },
+    }
+    print!("{}", game.hints_to_string());
+    Ok(())
+}
+
+/// Prints how hard each puzzle is, easiest first
+fn rate(paths: &[&String]) -> Result<()> {
+    let mut rated = Vec::new();
+    match paths {
+        [] => rated.push(("sample".to_string(), sample_game().rate())),
+        paths => {
+            for path in paths {
+                rated.push((path.to_string(), load_game(Some(path))?.rate()));
+            }
+        }
+    }
+    // Puzzles without a unique solution sort to the end
+    rated.sort_by_key(|(_, difficulty)| {
+        difficulty
+            .as_ref()
+            .map_or(usize::MAX, |difficulty| difficulty.score)
+    });
+    for (name, difficulty) in rated {
+        match difficulty {
+            Some(difficulty) => println!(
+                "{:>7} {:<7} {} (passes {}, probes {}, guesses {}, depth {})",
+                difficulty.score,
+                difficulty.category,
+                name,
+                difficulty.stats.passes,
+                difficulty.stats.probes,
+                difficulty.stats.guesses,
+                difficulty.stats.max_depth
+            ),
+            None => println!("{:>7} {:<7} {}", "-", "unsolvable", name),
+        }
+    }
+    Ok(())
+}
+
+/// Runs line logic on a blank copy of game with line_solver. Gives how it went, how long it
+/// took and the grid it ended up with
+fn run_line_solver<L: line_solver::LineSolver + Clone>(
+    game: &Game,
+    line_solver: L,
+) -> (game::Outcome, game::SolveStats, Duration, game::Grid) {
+    let mut blank = game.clone();
+    blank.grid = vec![vec![game::Square::Unknown; game.cols]; game.rows];
+    let mut solver = game::Solver::with_line_solver(blank, line_solver);
+    let start = Instant::now();
+    let outcome = solver.solve(&mut None);
+    (outcome, solver.stats, start.elapsed(), solver.game.grid)
+}
+
+/// Solves each puzzle with every line solver and prints how they got on, so they can be checked
+/// against each other. Brute force only gets tried when it won't take forever. Last is complete
+/// again with a cache in front, along with how much use the cache was
+fn compare(paths: &[&String]) -> Result<()> {
+    let games = match paths {
+        [] => vec![("sample".to_string(), sample_game())],
+        paths => paths
             .iter()
-            .map(|hint| {
-                hint.iter()
-                    .map(|seg| seg.to_string())
-                    .collect::<Vec<_>>()
-                    .join(" ")
-            })
-            .collect::<Vec<_>>()
-            .join("\n")
-            .as_bytes(),
-    )?;
-    file.write(b"\n\n");
-    let mut test_game = game::Game::new(col_hints, row_hints)?;
-    let mut solver = game::Solver::new(test_game);
-
-    solver.solve(&mut Some(&mut file));
-
-    let mut stdout = io::stdout();
-    // let _ = execute!(
-    //     stdout,
-    //     terminal::EnterAlternateScreen,
-    //     cursor::MoveTo(0, 0),
-    //     Print(render::double_vec_to_string(solver.game.render_all()))
-    // )?;
-
-    // std::thread::sleep(Duration::from_secs(1));
-    // execute!(stdout, terminal::LeaveAlternateScreen)?;
+            .map(|path| Ok((path.to_string(), load_game(Some(path))?)))
+            .collect::<Result<_>>()?,
+    };
+    for (name, game) in games {
+        println!("{}", name);
+        let mut runs = vec![
+            ("overlap", run_line_solver(&game, line_solver::Overlap)),
+            ("complete", run_line_solver(&game, line_solver::Complete)),
+        ];
+        match line_solver::BruteForce::fits(&game) {
+            true => runs.push((
+                "brute force",
+                run_line_solver(&game, line_solver::BruteForce),
+            )),
+            false => println!("  {:<12} too big to try", "brute force"),
+        }
+        // Clones share the cache, so this one can say how the run went after
+        let cached = line_solver::Cached::new(line_solver::Complete);
+        runs.push(("cached", run_line_solver(&game, cached.clone())));
+        for (solver, (outcome, stats, time, _)) in &runs {
+            println!(
+                "  {:<12} {:<14} passes {}, lines refined {}, {:.2?}",
+                solver,
+                format!("{:?}", outcome).to_lowercase(),
+                stats.passes,
+                stats.lines_refined,
+                time
+            );
+        }
+        let cache = cached.stats();
+        println!(
+            "  {:<12} {} hits, {} misses",
+            "cache", cache.hits, cache.misses
+        );
+        // Complete and brute force find everything a line can tell, so they should always match
+        if let [_, (_, complete), (_, brute_force), _] = &runs[..] {
+            if complete.3 != brute_force.3 {
+                println!("  complete and brute force worked out different grids!");
+            }
+        }
+    }
     Ok(())
 }
+
+/// Parses a COLSxROWS size flag
+fn parse_size(size: &str) -> Result<(usize, usize)> {
+    let Some((cols, rows)) = size.split_once('x') else {
+        bail!("size should look like 20x15");
+    };
+    Ok((cols.parse()?, rows.parse()?))
+}
+
+fn main() -> Result<()> {
+    let args: Vec<String> = env::args().collect();
+    let (flags, positional): (Vec<_>, Vec<_>) =
+        args.iter().skip(2).partition(|arg| arg.starts_with("--"));
+    let has_flag = |flag: &str| flags.iter().any(|arg| *arg == flag);
+    // For --flag=value style flags
+    let flag_value = |flag: &str| {
+        flags
+            .iter()
+            .find_map(|arg| arg.strip_prefix(flag)?.strip_prefix('='))
+    };
+    match args.get(1).map(|arg| arg.as_str()) {
+        Some("solve") => solve(load_game(positional.first().copied())?),
+        Some("svg") => svg(
+            load_game(positional.first().copied())?,
+            has_flag("--solution"),
+        ),
+        Some("image") => {
+            let (puzzle, out) = match positional[..] {
+                [out] => (None, out),
+                [puzzle, out] => (Some(puzzle), out),
+                _ => bail!(USAGE),
+            };
+            let scale = match flag_value("--scale") {
+                Some(scale) => scale.parse()?,
+                None => 1,
+            };
+            image(load_game(puzzle)?, out, scale)
+        }
+        Some("import") => {
+            let Some(path) = positional.first() else {
+                bail!(USAGE);
+            };
+            let binarize = match (has_flag("--dither"), flag_value("--threshold")) {
+                (true, _) => import::Binarize::Dither,
+                (false, Some(level)) => import::Binarize::Threshold(level.parse()?),
+                (false, None) => import::Binarize::Threshold(128),
+            };
+            import(path, flag_value("--size"), binarize)
+        }
+        Some("generate") => {
+            let mut options = generate::GeneratorOptions {
+                search: has_flag("--search"),
+                ..Default::default()
+            };
+            if let Some(size) = flag_value("--size") {
+                (options.cols, options.rows) = parse_size(size)?;
+            }
+            if let Some(tier) = flag_value("--tier") {
+                let Some(tier) = difficulty::Tier::from_name(tier) else {
+                    bail!(
+                        "unknown tier {}, try overlap, line, probing or backtracking",
+                        tier
+                    );
+                };
+                options.tier = Some(tier);
+            }
+            if let Some(density) = flag_value("--density") {
+                options.density = density.parse()?;
+            }
+            options.seed = match flag_value("--seed") {
+                Some(seed) => seed.parse()?,
+                None => std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)?
+                    .as_secs(),
+            };
+            // So a puzzle made without a seed can be made again
+            eprintln!("seed: {}", options.seed);
+            let Some(game) = generate::generate(&options) else {
+                bail!("gave up without finding a puzzle, that tier may not turn up at that size");
+            };
+            print!("{}", game.hints_to_string());
+            Ok(())
+        }
+        Some("rate") => rate(&positional),
+        Some("compare") => compare(&positional),
+        Some("cube") => match positional.first() {
+            Some(path) => cube(path),
+            None => bail!(USAGE),
+        },
+        Some("hex") => match positional.first() {
+            Some(path) => hex(path),
+            None => bail!(USAGE),
+        },
+        Some("edit") => {
+            let puzzle = positional.first().copied();
+            // Saves over the file being edited unless told otherwise. Opening fails if there's
+            // no picture to be had from the clues, so that can't blank the file
+            let out = match (flag_value("--out"), puzzle) {
+                (Some(out), _) => out.to_string(),
+                (None, Some(puzzle)) => puzzle.clone(),
+                (None, None) => "puzzle.txt".to_string(),
+            };
+            let mut edit = match (puzzle, flag_value("--size")) {
+                (Some(puzzle), _) => edit::Edit::open(load_game(Some(puzzle))?, out)?,
+                (None, size) => {
+                    let (cols, rows) = parse_size(size.unwrap_or("10x10"))?;
+                    if cols == 0 || rows == 0 {
+                        bail!("can't draw on an empty grid");
+                    }
+                    edit::Edit::blank(rows, cols, out)
+                }
+            };
+            edit.run()
+        }
+        Some("play") => {
+            let mut game = load_game(positional.first().copied())?;
+            // Mistake checking needs a reference grid, so work it out up front if the file
+            // didn't come with one
+            game.find_solution();
+            play::Play::new(game).run()
+        }
+        _ => bail!(USAGE),
+    }
+}
@@ -0,0 +1,394 @@
+use thiserror::Error;
+
+use crate::game::{Color, Corner, Game, Hint, Job, PaletteColor, Segment, Square};
+
+/// Puzzle files are just the row hints, a blank line, then the col hints. One hint per line with
+/// the segments separated by spaces. A line with no segments is written as 0.
+/// Optionally, another blank line and then the solution can follow, one row per line with o for
+/// filled and x for empty (spaces are ignored).
+/// EX:
+/// 2
+/// 1 1
+///
+/// 1 1
+/// 2
+///
+/// oo
+/// ox
+///
+/// Color puzzles start with a line listing their colors as symbol:rrggbb, after black which is
+/// always there as o. Segments that aren't black have their color's symbol stuck on the end, and
+/// the solution uses the symbols too. Segments of different colors don't need a gap between them.
+/// EX:
+/// colors r:ff0000
+/// 1 1r
+/// 2r
+///
+/// 1
+/// 2r
+///
+/// or
+/// rr
+///
+/// Triangle puzzles split some squares in half along the diagonal, written as ◤ ◥ ◣ ◢ by which
+/// corner is filled. Segments can start and end with one, written either side of the length (or
+/// just in front for a segment that's a lone triangle). Two segments only need a gap between them
+/// if the squares that meet both cover the side they meet on.
+/// EX:
+/// ◢1
+/// ◢2
+///
+/// ◢1
+/// ◢2
+///
+/// x◢
+/// ◢o
+///
+/// Mystery puzzles leave some of the clues out. A segment whose length isn't given is written ?
+/// (with its color on the end as usual), and a line whose whole clue is left out is written *.
+/// EX:
+/// ? 1
+/// *
+///
+/// 2
+/// ?
+/// 1
+///
+/// Mega puzzles have clues covering 2 neighbouring rows or cols at once. The clue goes on the
+/// first line as usual and the second line is just ^. Each number is the size of a group of
+/// filled squares touching side on, across the 2 lines as well as along them.
+/// EX:
+/// 3
+/// ^
+///
+/// 1
+/// 2
+///
+/// ox
+/// oo
+///
+/// Puzzles where rows and cols wrap round have a line saying wrap before the hints. A segment
+/// that runs off the end of a line and carries on from the start goes last in its hint.
+/// EX:
+/// wrap
+/// 2
+/// 0
+/// 1
+///
+/// 1
+/// 1
+/// 1
+///
+/// oxo
+/// xxx
+/// xox
+#[derive(Debug, Error)]
+pub enum ParseError {
+    #[error("missing blank line between row hints and col hints")]
+    MissingSeparator,
+    #[error("line {line}: bad segment {segment:?}")]
+    BadSegment { line: usize, segment: String },
+    #[error("puzzle has no rows or no cols")]
+    Empty,
+    #[error("line {line}: solution row doesn't match the puzzle")]
+    BadSolution { line: usize },
+    #[error("line {line}: bad color {color:?}, should look like r:ff0000")]
+    BadColor { line: usize, color: String },
+    #[error("line {line}: ^ has to follow a plain black clue, and can't be next to another ^")]
+    BadMega { line: usize },
+    #[error("line {line}: {problem}")]
+    BadLayout { line: usize, problem: &'static str },
+}
+
+/// Reads a line of colors, if it is one. Black comes first whether or not it's listed
+fn parse_palette(line_num: usize, line: &str) -> Option<Result<Vec<PaletteColor>, ParseError>> {
+    let colors = line.strip_prefix("colors")?;
+    let parse_color = |color: &str| {
+        let bad = || ParseError::BadColor {
+            line: line_num,
+            color: color.to_string(),
+        };
+        let (symbol, hex) = color.split_once(':').ok_or_else(bad)?;
+        let mut symbol_chars = symbol.chars();
+        let (Some(symbol), None) = (symbol_chars.next(), symbol_chars.next()) else {
+            return Err(bad());
+        };
+        // Symbols can't look like anything else in the file
+        if symbol.is_ascii_digit() || "ox_?*^".contains(symbol) {
+            return Err(bad());
+        }
+        let rgb = u32::from_str_radix(hex, 16).map_err(|_| bad())?;
+        if hex.len() != 6 {
+            return Err(bad());
+        }
+        Ok(PaletteColor {
+            symbol,
+            rgb: ((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8),
+        })
+    };
+    Some(
+        std::iter::once(Ok(PaletteColor::black()))
+            .chain(colors.split_whitespace().map(parse_color))
+            .collect(),
+    )
+}
+
+/// Palette index of a symbol
+fn color_of(palette: &[PaletteColor], symbol: char) -> Option<Color> {
+    palette
+        .iter()
+        .position(|color| color.symbol == symbol)
+        .map(|color| color as Color)
+}
+
+/// Reads a line of hints. Triangles are checked against the direction they're read in, so col
+/// hints need along_col
+pub fn parse_hint(
+    line_num: usize,
+    line: &str,
+    palette: &[PaletteColor],
+    along_col: bool,
+) -> Result<Hint, ParseError> {
+    if line == "*" {
+        return Ok(Segment::missing_clue());
+    }
+    line.split_whitespace()
+        .map(|seg| {
+            let bad = || ParseError::BadSegment {
+                line: line_num,
+                segment: seg.to_string(),
+            };
+            // Triangle in front, if any
+            let mut rest = seg;
+            let start = rest.chars().next().and_then(Corner::from_glyph);
+            if let Some(corner) = start {
+                rest = &rest[corner.glyph().len_utf8()..];
+            }
+            // Color symbol on the end, if any
+            let color = match rest.char_indices().last() {
+                Some((i, symbol))
+                    if !symbol.is_ascii_digit()
+                        && symbol != '?'
+                        && Corner::from_glyph(symbol).is_none() =>
+                {
+                    rest = &rest[..i];
+                    color_of(palette, symbol).ok_or_else(bad)?
+                }
+                _ => 0,
+            };
+            // Then the triangle on the end
+            let end = rest.chars().last().and_then(Corner::from_glyph);
+            if let Some(corner) = end {
+                rest = &rest[..rest.len() - corner.glyph().len_utf8()];
+            }
+            let segment = match rest {
+                "?" => Segment {
+                    start,
+                    end,
+                    ..Segment::hidden(color)
+                },
+                _ => {
+                    let len = rest.parse::<u32>().map_err(|_| bad())?;
+                    Segment {
+                        start,
+                        // A lone triangle only gets written once
+                        end: match len {
+                            1 => end.or(start),
+                            _ => end,
+                        },
+                        ..Segment::new(len, color)
+                    }
+                }
+            };
+            let along = match along_col {
+                true => segment.transposed(),
+                false => segment,
+            };
+            match along.is_valid() {
+                true => Ok(segment),
+                false => Err(bad()),
+            }
+        })
+        // A lone 0 means an empty line, which is the same as no segments
+        .filter(|seg| !matches!(seg, Ok(Segment { len: 0, .. })))
+        .collect()
+}
+
+fn parse_solution_row(
+    line_num: usize,
+    line: &str,
+    cols: usize,
+    palette: &[PaletteColor],
+) -> Result<Vec<Square>, ParseError> {
+    let row = line
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| match c {
+            'x' => Ok(Square::Empty),
+            c => match Corner::from_glyph(c) {
+                Some(corner) => Ok(Square::Triangle(corner)),
+                None => color_of(palette, c)
+                    .map(Square::Filled)
+                    .ok_or(ParseError::BadSolution { line: line_num }),
+            },
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    match row.len() == cols {
+        true => Ok(row),
+        false => Err(ParseError::BadSolution { line: line_num }),
+    }
+}
+
+impl Game {
+    pub fn from_hints_str(s: &str) -> Result<Game, ParseError> {
+        let lines: Vec<_> = s.lines().map(|line| line.trim()).collect();
+        // Skip any leading blank lines so files can start however
+        let mut start = lines
+            .iter()
+            .position(|line| !line.is_empty())
+            .ok_or(ParseError::Empty)?;
+        let mut palette = vec![PaletteColor::black()];
+        let mut wrap = false;
+        // Header lines, in whatever order
+        loop {
+            match (lines[start], parse_palette(start + 1, lines[start])) {
+                ("wrap", _) => wrap = true,
+                (_, Some(colors)) => palette = colors?,
+                _ => break,
+            }
+            start += lines[start + 1..]
+                .iter()
+                .position(|line| !line.is_empty())
+                .ok_or(ParseError::Empty)?
+                + 1;
+        }
+        let separator = lines[start..]
+            .iter()
+            .position(|line| line.is_empty())
+            .map(|i| i + start)
+            .ok_or(ParseError::MissingSeparator)?;
+
+        // ^ lines get sorted out once the game exists, they're missing until then
+        let parse_hints = |range: std::ops::Range<usize>, along_col: bool| {
+            range
+                .map(|i| match lines[i] {
+                    "^" => Ok(Segment::missing_clue()),
+                    line => parse_hint(i + 1, line, &palette, along_col),
+                })
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let row_hints = parse_hints(start..separator, false)?;
+        let col_end = lines[separator + 1..]
+            .iter()
+            .position(|line| line.is_empty())
+            .map(|i| i + separator + 1)
+            .unwrap_or(lines.len());
+        let col_hints = parse_hints(separator + 1..col_end, true)?;
+
+        if row_hints.is_empty() || col_hints.is_empty() {
+            return Err(ParseError::Empty);
+        }
+        // Every hint has to fit its line, rows being as long as there are cols and the other way
+        // round. Col hints get measured the way the line solvers see them. A mega clue's groups
+        // spread over 2 lines so the line it starts on can go over
+        let rows = (start..separator).zip(row_hints.iter().cloned());
+        let cols = (separator + 1..col_end)
+            .zip(&col_hints)
+            .map(|(i, hint)| (i, hint.iter().map(Segment::transposed).collect()));
+        for (i, hint) in rows.chain(cols) {
+            let len = match i < separator {
+                true => col_hints.len(),
+                false => row_hints.len(),
+            };
+            if lines.get(i + 1) != Some(&"^") && Segment::min_len(&hint) > len {
+                return Err(ParseError::BadLayout {
+                    line: i + 1,
+                    problem: "hint is too long for its line",
+                });
+            }
+        }
+        let cols = col_hints.len();
+        let mut game =
+            Game::with_palette(col_hints, row_hints, palette).expect("hints were all checked");
+        game.wrap = wrap;
+
+        let megas = (start..separator)
+            .map(|i| (i, Job::Row(i - start)))
+            .chain((separator + 1..col_end).map(|i| (i, Job::Col(i - separator - 1))));
+        for (i, job) in megas.filter(|(i, _)| lines[*i] == "^") {
+            let bad = || ParseError::BadMega { line: i + 1 };
+            let first = match job {
+                Job::Row(row) => row.checked_sub(1).map(Job::Row),
+                Job::Col(col) => col.checked_sub(1).map(Job::Col),
+            }
+            .ok_or_else(bad)?;
+            let (hint, _) = game.get_line(first);
+            let plain = hint.iter().all(|seg| {
+                seg.color == 0 && seg.start.is_none() && seg.end.is_none() && !seg.hidden
+            });
+            if !plain || lines[i - 1] == "^" {
+                return Err(bad());
+            }
+            let groups = hint.iter().map(|seg| seg.len).collect();
+            game.add_mega(first, groups).map_err(|_| bad())?;
+        }
+
+        let solution_lines: Vec<usize> = (col_end..lines.len())
+            .filter(|i| !lines[*i].is_empty())
+            .collect();
+        let solution = solution_lines
+            .iter()
+            .map(|i| parse_solution_row(i + 1, lines[*i], cols, &game.palette))
+            .collect::<Result<Vec<_>, _>>()?;
+        if !solution.is_empty() {
+            if solution.len() != game.rows {
+                return Err(ParseError::BadSolution { line: lines.len() });
+            }
+            // Checked against the clues by filling it in, then the grid goes back to blank
+            let blank = std::mem::replace(&mut game.grid, solution);
+            if !game.is_solved() {
+                return Err(ParseError::BadSolution {
+                    line: solution_lines[0] + 1,
+                });
+            }
+            game.solution = Some(std::mem::replace(&mut game.grid, blank));
+        }
+        Ok(game)
+    }
+
+    pub fn hints_to_string(&self) -> String {
+        let hint_to_string = |labels: Vec<String>| match labels.is_empty() {
+            true => "0".to_string(),
+            false => labels.join(" "),
+        };
+        let rows = self.hint_labels(false).into_iter().map(hint_to_string);
+        let cols = self.hint_labels(true).into_iter().map(hint_to_string);
+        let mut out = String::new();
+        if self.palette.len() > 1 {
+            out.push_str("colors");
+            for color in &self.palette[1..] {
+                let (r, g, b) = color.rgb;
+                out.push_str(&format!(" {}:{:02x}{:02x}{:02x}", color.symbol, r, g, b));
+            }
+            out.push('\n');
+        }
+        if self.wrap {
+            out.push_str("wrap\n");
+        }
+        out.push_str(&rows.collect::<Vec<_>>().join("\n"));
+        out.push_str("\n\n");
+        out.push_str(&cols.collect::<Vec<_>>().join("\n"));
+        out.push('\n');
+        if let Some(solution) = &self.solution {
+            out.push('\n');
+            for row in solution {
+                out.extend(row.iter().map(|square| match square {
+                    Square::Filled(_) | Square::Triangle(_) => self.symbol(square),
+                    _ => 'x',
+                }));
+                out.push('\n');
+            }
+        }
+        out
+    }
+}
@@ -0,0 +1,526 @@
+use std::io::{self, Write};
+
+use anyhow::Result;
+use crossterm::{
+    cursor,
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind,
+        KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+    },
+    execute, queue,
+    style::Print,
+    terminal::{self, ClearType},
+};
+
+use crate::{
+    game::{Color, Corner, Deduction, Game, Job, Segment, Square},
+    render::Highlight,
+};
+
+const HELP: &str = "arrows/hjkl: move  space: fill  x: cross  shift+move: drag  \
+                    u/U: undo/redo  ?: hint  e: explain  c: check  m: mistake mode  \
+                    a: auto-cross  g: grid lines  1-9: color  t: triangle  q: quit";
+
+/// One undoable step, as (row, col, before, after) for every square it touched
+type Action = Vec<(usize, usize, Square, Square)>;
+
+/// Painting the same square over a run of cells, like holding the button down in Picross.
+/// Started by filling/crossing a square, then dragged with the mouse or shift+move
+struct Stroke {
+    start: (usize, usize),
+    /// What the stroke paints, Unknown when it started by clearing a square
+    square: Square,
+    /// The line the stroke is locked to, picked by the first move away from start
+    job: Option<Job>,
+    /// Length of the undo stack when the stroke started so the whole stroke undoes at once
+    undo_len: usize,
+    /// Number of cells the stroke covers
+    len: usize,
+}
+
+/// What to do about squares that disagree with the known solution
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MistakeMode {
+    /// Don't say anything until asked with check board
+    Off,
+    /// Mark wrong squares in red as soon as they're placed
+    Flag,
+    /// Picross style. Wrong moves get corrected on the spot and counted against you
+    Count,
+}
+
+impl MistakeMode {
+    fn next(self) -> Self {
+        match self {
+            MistakeMode::Off => MistakeMode::Flag,
+            MistakeMode::Flag => MistakeMode::Count,
+            MistakeMode::Count => MistakeMode::Off,
+        }
+    }
+}
+
+/// Interactive play mode. game.grid is the player's grid
+pub struct Play {
+    pub game: Game,
+    /// (row, col) of the cursor
+    pub cursor: (usize, usize),
+    /// Whether hints come with reasons
+    pub explain: bool,
+    pub mistake_mode: MistakeMode,
+    /// Number of wrong moves made in MistakeMode::Count
+    pub mistakes: u32,
+    /// Cross out the rest of a line as soon as it matches its hint
+    pub auto_cross: bool,
+    /// Palette color filling paints with. 1-9 pick it in color puzzles
+    pub color: Color,
+    undo: Vec<Action>,
+    redo: Vec<Action>,
+    stroke: Option<Stroke>,
+    hint: Option<Deduction>,
+    /// Set by check board, shows every wrong square until the next move
+    checking: bool,
+    message: Vec<String>,
+}
+
+impl Play {
+    pub fn new(game: Game) -> Self {
+        Play {
+            game,
+            cursor: (0, 0),
+            explain: false,
+            mistake_mode: MistakeMode::Off,
+            mistakes: 0,
+            auto_cross: false,
+            color: 0,
+            undo: vec![],
+            redo: vec![],
+            stroke: None,
+            hint: None,
+            checking: false,
+            message: vec![],
+        }
+    }
+
+    pub fn run(&mut self) -> Result<()> {
+        let mut stdout = io::stdout();
+        terminal::enable_raw_mode()?;
+        execute!(stdout, terminal::EnterAlternateScreen, EnableMouseCapture)?;
+
+        let result = self.event_loop(&mut stdout);
+
+        // Always try to give the terminal back, even if the loop blew up
+        execute!(stdout, DisableMouseCapture, terminal::LeaveAlternateScreen)?;
+        terminal::disable_raw_mode()?;
+        result
+    }
+
+    fn event_loop(&mut self, stdout: &mut io::Stdout) -> Result<()> {
+        loop {
+            self.draw(stdout)?;
+            match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press && !self.handle_key(key) => {
+                    return Ok(());
+                }
+                Event::Mouse(mouse) => self.handle_mouse(mouse),
+                _ => {}
+            }
+        }
+    }
+
+    /// Returns false when the player wants out
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        let (row, col) = self.cursor;
+        let (last_row, last_col) = (self.game.rows - 1, self.game.cols - 1);
+        let shift = key.modifiers.contains(KeyModifiers::SHIFT);
+        let drag_to = match key.code {
+            KeyCode::Up if shift => Some((row.saturating_sub(1), col)),
+            KeyCode::Down if shift => Some(((row + 1).min(last_row), col)),
+            KeyCode::Left if shift => Some((row, col.saturating_sub(1))),
+            KeyCode::Right if shift => Some((row, (col + 1).min(last_col))),
+            KeyCode::Char('K') => Some((row.saturating_sub(1), col)),
+            KeyCode::Char('J') => Some(((row + 1).min(last_row), col)),
+            KeyCode::Char('H') => Some((row, col.saturating_sub(1))),
+            KeyCode::Char('L') => Some((row, (col + 1).min(last_col))),
+            _ => None,
+        };
+        if let Some((row, col)) = drag_to {
+            self.extend_stroke(row, col);
+            return true;
+        }
+        // Anything else lets go of the stroke
+        self.stroke = None;
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Up | KeyCode::Char('k') => self.cursor.0 = row.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => self.cursor.0 = (row + 1).min(last_row),
+            KeyCode::Left | KeyCode::Char('h') => self.cursor.1 = col.saturating_sub(1),
+            KeyCode::Right | KeyCode::Char('l') => self.cursor.1 = (col + 1).min(last_col),
+            KeyCode::Char(' ') | KeyCode::Char('f') => self.toggle(Square::Filled(self.color)),
+            KeyCode::Char('x') => self.toggle(Square::Empty),
+            KeyCode::Char('t') => {
+                // Turns the triangle round a corner at a time, and back to unknown after the
+                // last one
+                let square = match &self.game.grid[row][col] {
+                    Square::Triangle(corner) => match corner.clockwise() {
+                        Some(next) => Square::Triangle(next),
+                        None => Square::Triangle(*corner),
+                    },
+                    _ => Square::Triangle(Corner::TopLeft),
+                };
+                self.toggle(square)
+            }
+            KeyCode::Char(digit @ '1'..='9') => {
+                let color = digit as usize - '1' as usize;
+                if color < self.game.palette.len() {
+                    self.color = color as Color;
+                    self.message = vec![format!("Color {}", digit)];
+                }
+            }
+            KeyCode::Char('u') => self.undo(),
+            KeyCode::Char('U') => self.redo(),
+            KeyCode::Char('a') => self.toggle_auto_cross(),
+            KeyCode::Char('g') => {
+                self.game.separator_every = match self.game.separator_every {
+                    Some(_) => None,
+                    None => Some(5),
+                }
+            }
+            KeyCode::Char('?') => self.show_hint(),
+            KeyCode::Char('c') => self.check_board(),
+            KeyCode::Char('m') => self.cycle_mistake_mode(),
+            KeyCode::Char('e') => {
+                self.explain = !self.explain;
+                self.message = vec![format!(
+                    "Explanations {}",
+                    if self.explain { "on" } else { "off" }
+                )];
+            }
+            _ => {}
+        }
+        true
+    }
+
+    pub fn handle_mouse(&mut self, mouse: MouseEvent) {
+        let cell = self.game.cell_at(mouse.column as usize, mouse.row as usize);
+        match (mouse.kind, cell) {
+            (MouseEventKind::Down(button), Some(cell)) => {
+                self.cursor = cell;
+                match button {
+                    MouseButton::Right => self.toggle(Square::Empty),
+                    _ => self.toggle(Square::Filled(self.color)),
+                }
+            }
+            (MouseEventKind::Drag(_), Some((row, col))) => self.extend_stroke(row, col),
+            (MouseEventKind::Up(_), _) => self.stroke = None,
+            _ => {}
+        }
+    }
+
+    /// Sets the square under the cursor to square, or back to Unknown if it already is that.
+    /// Also starts a stroke that keeps painting whatever it set
+    fn toggle(&mut self, square: Square) {
+        let (row, col) = self.cursor;
+        self.message.clear();
+
+        let new = match self.game.grid[row][col] == square {
+            true => Square::Unknown,
+            false => square,
+        };
+        self.stroke = Some(Stroke {
+            start: self.cursor,
+            square: new.clone(),
+            job: None,
+            undo_len: self.undo.len(),
+            len: 1,
+        });
+        if !self.paint(row, col, new) {
+            self.stroke = None;
+        }
+        self.after_move();
+    }
+
+    /// Drags the current stroke towards (row, col), painting every cell between it and the start.
+    /// Once a stroke goes one way it stays locked to that row or col, so the target gets
+    /// projected onto it. Without a stroke this just moves the cursor
+    fn extend_stroke(&mut self, row: usize, col: usize) {
+        let Some(stroke) = &mut self.stroke else {
+            self.cursor = (row, col);
+            return;
+        };
+        let (start_row, start_col) = stroke.start;
+        if (row, col) == stroke.start {
+            self.cursor = (row, col);
+            return;
+        }
+        let job =
+            *stroke
+                .job
+                .get_or_insert(match row.abs_diff(start_row) > col.abs_diff(start_col) {
+                    true => Job::Col(start_col),
+                    false => Job::Row(start_row),
+                });
+        let (start, end) = match job {
+            Job::Row(_) => (start_col, col),
+            Job::Col(_) => (start_row, row),
+        };
+        stroke.len = start.abs_diff(end) + 1;
+        let square = stroke.square.clone();
+        let undo_len = stroke.undo_len;
+        self.cursor = job.cell(end);
+
+        self.message.clear();
+        let indices: Vec<usize> = match start <= end {
+            true => (start..=end).collect(),
+            false => (end..=start).rev().collect(),
+        };
+        for i in indices {
+            let (row, col) = job.cell(i);
+            if !self.paint(row, col, square.clone()) {
+                self.stroke = None;
+                break;
+            }
+        }
+        // Squash everything the stroke did into one action
+        if self.undo.len() > undo_len + 1 {
+            let merged: Action = self.undo.drain(undo_len..).flatten().collect();
+            self.undo.push(merged);
+        }
+        self.after_move();
+    }
+
+    /// Sets a square as a move by the player, with mistake checking and auto-cross.
+    /// Returns false if it was a mistake that got corrected
+    fn paint(&mut self, row: usize, col: usize, new: Square) -> bool {
+        let answer = self.game.solution.as_ref().map(|s| s[row][col].clone());
+        let (square, ok) = match answer {
+            Some(answer)
+                if self.mistake_mode == MistakeMode::Count
+                    && new != Square::Unknown
+                    && new != answer =>
+            {
+                self.mistakes += 1;
+                self.message = vec![format!(
+                    "Wrong! That one is {}",
+                    match answer {
+                        Square::Filled(_) => "filled",
+                        Square::Triangle(_) => "a triangle",
+                        _ => "empty",
+                    }
+                )];
+                (answer, false)
+            }
+            _ => (new, true),
+        };
+        let before = std::mem::replace(&mut self.game.grid[row][col], square.clone());
+        // The crosses go in the same action as the move so one undo takes both back
+        let crosses = match self.auto_cross {
+            true => self.auto_cross_action(&[Job::Row(row), Job::Col(col)]),
+            false => vec![],
+        };
+        self.game.grid[row][col] = before.clone();
+        self.apply([vec![(row, col, before, square)], crosses].concat());
+        ok
+    }
+
+    /// Does an action and puts it on the undo stack. Empty actions are dropped so undo never
+    /// seems to do nothing
+    fn apply(&mut self, mut action: Action) {
+        action.retain(|(_, _, before, after)| before != after);
+        if action.is_empty() {
+            return;
+        }
+        for (row, col, _, after) in &action {
+            self.game.grid[*row][*col] = after.clone();
+        }
+        self.undo.push(action);
+        self.redo.clear();
+    }
+
+    pub fn undo(&mut self) {
+        let Some(action) = self.undo.pop() else {
+            self.message = vec!["Nothing to undo".to_string()];
+            return;
+        };
+        // Backwards since a merged stroke can touch the same square more than once
+        for (row, col, before, _) in action.iter().rev() {
+            self.game.grid[*row][*col] = before.clone();
+        }
+        self.redo.push(action);
+        self.message.clear();
+        self.after_move();
+    }
+
+    pub fn redo(&mut self) {
+        let Some(action) = self.redo.pop() else {
+            self.message = vec!["Nothing to redo".to_string()];
+            return;
+        };
+        for (row, col, _, after) in &action {
+            self.game.grid[*row][*col] = after.clone();
+        }
+        self.undo.push(action);
+        self.message.clear();
+        self.after_move();
+    }
+
+    fn after_move(&mut self) {
+        // Any hint that was up is probably stale now
+        self.hint = None;
+        self.checking = false;
+        if self.is_solved() {
+            self.message = vec!["Solved!".to_string()];
+        }
+    }
+
+    /// Crosses out every Unknown square in whichever of jobs already match their hints
+    fn auto_cross_action(&self, jobs: &[Job]) -> Action {
+        let mut action: Action = Vec::new();
+        for job in jobs {
+            let (hint, line) = self.game.get_line(*job);
+            // Lines with no clue of their own always match, which says nothing about them
+            if Segment::is_missing(&hint) || !self.game.matches_hint(&hint, &line) {
+                continue;
+            }
+            for (i, square) in line.iter().enumerate() {
+                let (row, col) = job.cell(i);
+                // A row and col can share a square, only cross it once
+                if *square == Square::Unknown && !action.iter().any(|a| (a.0, a.1) == (row, col)) {
+                    action.push((row, col, Square::Unknown, Square::Empty));
+                }
+            }
+        }
+        action
+    }
+
+    fn toggle_auto_cross(&mut self) {
+        self.auto_cross = !self.auto_cross;
+        self.message = vec![format!(
+            "Auto-cross {}",
+            if self.auto_cross { "on" } else { "off" }
+        )];
+        if self.auto_cross {
+            // Catch up on any lines finished while it was off
+            let jobs: Vec<_> = (0..self.game.rows)
+                .map(Job::Row)
+                .chain((0..self.game.cols).map(Job::Col))
+                .collect();
+            let action = self.auto_cross_action(&jobs);
+            self.apply(action);
+            self.hint = None;
+        }
+    }
+
+    pub fn is_solved(&self) -> bool {
+        self.game.is_solved()
+    }
+
+    pub fn show_hint(&mut self) {
+        self.hint = None;
+        self.message = match self.game.next_deduction() {
+            Err(job) => {
+                // Highlight the broken line's clue with no cells so the player knows where to look
+                self.hint = Some(Deduction {
+                    job,
+                    cells: vec![],
+                    reasons: vec![],
+                });
+                vec![format!(
+                    "{} can't fit its clue anymore, something in it is wrong",
+                    describe(job)
+                )]
+            }
+            Ok(None) if self.is_solved() => vec!["Already solved!".to_string()],
+            Ok(None) => vec!["No single line deduction left, try guessing".to_string()],
+            Ok(Some(deduction)) => {
+                let mut message = vec![format!(
+                    "Look at {}, {} square(s) can be worked out",
+                    describe(deduction.job),
+                    deduction.cells.len()
+                )];
+                if self.explain {
+                    message.extend(deduction.reasons.iter().cloned());
+                }
+                self.hint = Some(deduction);
+                message
+            }
+        };
+    }
+
+    pub fn check_board(&mut self) {
+        self.message = match self.game.solution.is_some() {
+            false => vec!["No known solution to check against".to_string()],
+            true => match self.game.mistakes().len() {
+                0 => vec!["No mistakes so far".to_string()],
+                n => vec![format!("{} wrong square(s)", n)],
+            },
+        };
+        self.checking = true;
+    }
+
+    fn cycle_mistake_mode(&mut self) {
+        if self.game.solution.is_none() {
+            self.message = vec!["No known solution, can't track mistakes".to_string()];
+            return;
+        }
+        self.mistake_mode = self.mistake_mode.next();
+        self.message = vec![format!("Mistake mode: {:?}", self.mistake_mode)];
+    }
+
+    fn highlight(&self) -> Highlight {
+        let mut highlight = Highlight {
+            clue_status: true,
+            cursor: Some(self.cursor),
+            ..Default::default()
+        };
+        if let Some(deduction) = &self.hint {
+            highlight.job = Some(deduction.job);
+            highlight.cells = deduction
+                .cells
+                .iter()
+                .map(|(i, _)| deduction.job.cell(*i))
+                .collect();
+        }
+        if self.checking || self.mistake_mode == MistakeMode::Flag {
+            highlight.errors = self.game.mistakes();
+        }
+        highlight
+    }
+
+    fn draw(&mut self, stdout: &mut io::Stdout) -> Result<()> {
+        let highlight = self.highlight();
+        let rendered = self.game.render_all(&highlight);
+        queue!(stdout, terminal::Clear(ClearType::All))?;
+        for (y, line) in rendered.iter().enumerate() {
+            queue!(stdout, cursor::MoveTo(0, y as u16), Print(line.concat()))?;
+        }
+        let mut counters = format!("Row {} Col {}", self.cursor.0 + 1, self.cursor.1 + 1);
+        if let Some(stroke) = &self.stroke {
+            counters.push_str(&format!("  Stroke: {}", stroke.len));
+        }
+        if self.game.palette.len() > 1 {
+            counters.push_str(&format!("  Color: {}", self.color + 1));
+        }
+        if self.mistake_mode == MistakeMode::Count {
+            counters.push_str(&format!("  Mistakes: {}", self.mistakes));
+        }
+        let status = std::iter::once(counters.as_str())
+            .chain(self.message.iter().map(|line| line.as_str()))
+            .chain([HELP]);
+        for (y, line) in (rendered.len() + 1..).zip(status) {
+            queue!(stdout, cursor::MoveTo(0, y as u16), Print(line))?;
+        }
+        // Park the terminal cursor on the grid cell we're on
+        let (x, y) = self.game.screen_pos(self.cursor.0, self.cursor.1);
+        queue!(stdout, cursor::MoveTo(x as u16, y as u16), cursor::Show)?;
+        stdout.flush()?;
+        Ok(())
+    }
+}
+
+fn describe(job: Job) -> String {
+    match job {
+        Job::Row(i) => format!("row {}", i + 1),
+        Job::Col(i) => format!("col {}", i + 1),
+    }
+}
@@ -0,0 +1,94 @@
+use anyhow::Result;
+
+use crate::game::{Color, Corner, Game, Square};
+
+/// Grey level for squares that aren't worked out yet, so half solved grids still show something
+const UNKNOWN_GREY: u8 = 160;
+
+impl Game {
+    /// The grid as 8 bit greyscale pixels, row by row, with every square blown up to a
+    /// scale x scale block. Filled is black (or however dark its color is), empty is white.
+    /// Triangles fill their half of the block, diagonal included, so at scale 1 they're just black.
+    /// Returns (width, height, pixels)
+    pub fn to_pixels(&self, scale: usize) -> (usize, usize, Vec<u8>) {
+        let (width, height) = (self.cols * scale, self.rows * scale);
+        let mut pixels = Vec::with_capacity(width * height);
+        for row in &self.grid {
+            for y in 0..scale {
+                for square in row {
+                    pixels.extend((0..scale).map(|x| self.pixel(square, x, y, scale)));
+                }
+            }
+        }
+        (width, height, pixels)
+    }
+
+    /// How dark a palette color is
+    fn grey(&self, color: Color) -> u8 {
+        let (r, g, b) = self.palette[color as usize].rgb;
+        ((299 * r as u32 + 587 * g as u32 + 114 * b as u32) / 1000) as u8
+    }
+
+    /// Grey for pixel (x, y) of a square's scale x scale block
+    fn pixel(&self, square: &Square, x: usize, y: usize, scale: usize) -> u8 {
+        match square {
+            Square::Filled(color) => self.grey(*color),
+            Square::Triangle(corner) => {
+                // Pixel centers, doubled so they stay whole numbers
+                let (x, y, size) = (2 * x + 1, 2 * y + 1, 2 * scale);
+                let inside = match corner {
+                    Corner::TopLeft => x + y <= size,
+                    Corner::TopRight => x >= y,
+                    Corner::BottomLeft => x <= y,
+                    Corner::BottomRight => x + y >= size,
+                };
+                match inside {
+                    true => self.grey(0),
+                    false => 255,
+                }
+            }
+            Square::Empty => 255,
+            Square::Unknown => UNKNOWN_GREY,
+        }
+    }
+
+    /// Binary PBM (P4). There's only black and white so unknown squares come out white, and
+    /// colors go black or white by how dark they are
+    pub fn to_pbm(&self, scale: usize) -> Vec<u8> {
+        let (width, height, pixels) = self.to_pixels(scale);
+        let mut out = format!("P4\n{} {}\n", width, height).into_bytes();
+        for line in pixels.chunks(width.max(1)) {
+            // 8 pixels to a byte, first pixel in the high bit, 1 means black
+            for byte in line.chunks(8) {
+                out.push(
+                    byte.iter()
+                        .enumerate()
+                        .filter(|(_, grey)| **grey < 128)
+                        .fold(0u8, |acc, (i, _)| acc | (0x80 >> i)),
+                );
+            }
+        }
+        out
+    }
+
+    /// Binary PGM (P5) with 8 bit greys
+    pub fn to_pgm(&self, scale: usize) -> Vec<u8> {
+        let (width, height, pixels) = self.to_pixels(scale);
+        let mut out = format!("P5\n{} {}\n255\n", width, height).into_bytes();
+        out.extend(pixels);
+        out
+    }
+
+    /// 8 bit greyscale PNG
+    pub fn to_png(&self, scale: usize) -> Result<Vec<u8>> {
+        let (width, height, pixels) = self.to_pixels(scale);
+        let mut out = Vec::new();
+        let mut encoder = png::Encoder::new(&mut out, width as u32, height as u32);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&pixels)?;
+        writer.finish()?;
+        Ok(out)
+    }
+}
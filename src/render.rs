@@ -1,20 +1,61 @@
 use std::fmt::Display;
 
 use anyhow::Result;
-use crossterm::style::Stylize;
+use crossterm::style::{self, Stylize};
 
-use crate::game::{Game, Hint, Square};
+use crate::game::{Corner, Game, Hint, Job, PaletteColor, Segment, SegmentStatus, Square};
 
 impl Display for Square {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Square::Unknown => write!(f, "{}", " ".on_white()),
-            Square::Filled => write!(f, "{}", "■".black().on_white()),
+            // Display doesn't know the palette so everything filled shows as black here, see
+            // render_grid for the real colors
+            Square::Filled(_) | Square::Triangle(_) => {
+                write!(f, "{}", self.glyph().black().on_white())
+            }
             Square::Empty => write!(f, "{}", "X".black().on_white()),
         }
     }
 }
 
+impl Square {
+    pub fn glyph(&self) -> &'static str {
+        match self {
+            Square::Unknown => " ",
+            Square::Filled(_) => "■",
+            Square::Triangle(Corner::TopLeft) => "◤",
+            Square::Triangle(Corner::TopRight) => "◥",
+            Square::Triangle(Corner::BottomLeft) => "◣",
+            Square::Triangle(Corner::BottomRight) => "◢",
+            Square::Empty => "X",
+        }
+    }
+}
+
+/// Terminal color for a palette entry
+fn term_color(color: &PaletteColor) -> style::Color {
+    let (r, g, b) = color.rgb;
+    style::Color::Rgb { r, g, b }
+}
+
+/// Extra stuff to draw attention to on top of the normal render. Used by play mode to show hints
+#[derive(Default)]
+pub struct Highlight {
+    /// Line whose clue gets highlighted
+    pub job: Option<Job>,
+    /// Grid cells to highlight as (row, col)
+    pub cells: Vec<(usize, usize)>,
+    /// Grid cells to mark as wrong as (row, col). These win over cells
+    pub errors: Vec<(usize, usize)>,
+    /// Grey out clue segments the grid has finished and turn clues red when their line is broken
+    pub clue_status: bool,
+    /// (row, col) to draw a crosshair through, lighting up its row and col and their clues
+    pub cursor: Option<(usize, usize)>,
+    /// Grid cells the hints don't pin down as (row, col), see Game::ambiguity
+    pub ambiguous: Vec<(usize, usize)>,
+}
+
 /*
 * object: 123
 *         456
@@ -49,12 +90,36 @@ pub fn double_vec_to_string<T: Display>(buffer: Vec<Vec<T>>) -> String {
 }
 
 impl Game {
-    pub fn render_hints(hints: &[Hint]) -> Vec<Vec<String>> {
+    /// Renders a bar of hints. The hint at index highlighted (if any) is drawn in yellow, and the
+    /// one at crosshair in cyan.
+    /// statuses has one entry per segment per hint and is used to grey out or redden segments,
+    /// pass an empty slice to draw everything plain. Segments that aren't black are drawn in
+    /// their color from palette.
+    /// joined has the first line of each pair under a mega clue. Both lines of the pair get a
+    /// blue background instead of grey so they read as one clue
+    pub fn render_hints(
+        hints: &[Hint],
+        statuses: &[Vec<SegmentStatus>],
+        highlighted: Option<usize>,
+        crosshair: Option<usize>,
+        palette: &[PaletteColor],
+        joined: &[usize],
+    ) -> Vec<Vec<String>> {
         // im just gonna overly abstract this logic bc "readability" or whatever tf that is
-        fn render_hint(hint: &Hint, max_segments: usize, max_digits: usize) -> Vec<String> {
+        #[allow(clippy::too_many_arguments)]
+        fn render_hint(
+            hint: &Hint,
+            statuses: Option<&Vec<SegmentStatus>>,
+            max_segments: usize,
+            max_digits: usize,
+            highlighted: bool,
+            crosshair: bool,
+            joined: bool,
+            palette: &[PaletteColor],
+        ) -> Vec<String> {
             let segments = hint.len();
             let mut dark_grey = false;
-            let mut padded_hint: Vec<Option<u32>> = Vec::with_capacity(max_segments);
+            let mut padded_hint: Vec<Option<Segment>> = Vec::with_capacity(max_segments);
             // hint_vec.extend(vec![None; max_segments - segments]);
             // womp womp thats an unecessary heap allocation so im doing this shit instead
             for _ in 0..max_segments - segments {
@@ -62,23 +127,44 @@ impl Game {
             }
             padded_hint.extend(hint.iter().map(|segment| Some(*segment)));
             let mut styled_hint_chars: Vec<String> = Vec::with_capacity(max_segments * max_digits); // The string stored in this vec is the chars in each segment. they are all len(max_digits)
-            for segment in padded_hint {
+            for (i, segment) in padded_hint.into_iter().enumerate() {
+                let status = match i.checked_sub(max_segments - segments) {
+                    Some(seg) => statuses.map_or(SegmentStatus::Open, |statuses| statuses[seg]),
+                    None => SegmentStatus::Open, // Padding
+                };
                 let mut padded_segment_chars: String = String::with_capacity(max_digits);
                 let segment_str = match segment {
                     Some(s) => s.to_string(),
                     None => "".to_string(),
                 };
+                let color = segment.map_or(0, |s| s.color);
                 // ya nvm idc, allocate deez nuts
-                padded_segment_chars.extend(vec![" "; max_digits - segment_str.len()]);
+                padded_segment_chars.extend(vec![" "; max_digits - segment_str.chars().count()]);
                 padded_segment_chars.push_str(&segment_str);
 
                 for character in padded_segment_chars.chars() {
-                    match dark_grey {
-                        true => {
-                            styled_hint_chars.push(character.black().on_dark_grey().to_string())
+                    // Done segments get faded text, which has to be whatever grey the background
+                    // isn't or it'd vanish
+                    let styled = match (status, dark_grey) {
+                        (SegmentStatus::Open, _) if color != 0 => {
+                            character.with(term_color(&palette[color as usize]))
                         }
-                        false => styled_hint_chars.push(character.black().on_grey().to_string()),
-                    }
+                        (SegmentStatus::Open, _) => character.black(),
+                        (SegmentStatus::Done, true) => character.grey(),
+                        (SegmentStatus::Done, false) => character.dark_grey(),
+                        (SegmentStatus::Violated, _) => character.white(),
+                    };
+                    let styled = match (highlighted, status, dark_grey) {
+                        (true, _, _) => styled.on_yellow(),
+                        (false, SegmentStatus::Violated, _) => styled.on_red(),
+                        (false, _, true) if crosshair => styled.on_dark_cyan(),
+                        (false, _, false) if crosshair => styled.on_cyan(),
+                        (false, _, true) if joined => styled.on_dark_blue(),
+                        (false, _, false) if joined => styled.on_blue(),
+                        (false, _, true) => styled.on_dark_grey(),
+                        (false, _, false) => styled.on_grey(),
+                    };
+                    styled_hint_chars.push(styled.to_string());
                 }
                 dark_grey = !dark_grey;
             }
@@ -88,26 +174,178 @@ impl Game {
         let max_digits = hints
             .iter()
             .flatten()
-            .map(|segment| segment.to_string().len())
+            .map(|segment| segment.to_string().chars().count())
             .max()
-            .unwrap();
+            .unwrap_or(1);
 
-        let max_segments = hints.iter().map(|hint| hint.len()).max().unwrap();
+        // At least 1 so a puzzle full of empty lines still gets a bar to draw in
+        let max_segments = hints
+            .iter()
+            .map(|hint| hint.len())
+            .max()
+            .unwrap_or(0)
+            .max(1);
 
         hints
             .iter()
-            .map(|hint| render_hint(hint, max_segments, max_digits))
+            .enumerate()
+            .map(|(i, hint)| {
+                render_hint(
+                    hint,
+                    statuses.get(i),
+                    max_segments,
+                    max_digits,
+                    highlighted == Some(i),
+                    crosshair == Some(i),
+                    joined.contains(&i) || i.checked_sub(1).is_some_and(|i| joined.contains(&i)),
+                    palette,
+                )
+            })
             .collect()
     }
 
-    pub fn render_grid(&self) -> Vec<Vec<String>> {
+    pub fn render_grid(&self, highlight: &Highlight) -> Vec<Vec<String>> {
         self.grid
             .iter()
-            .map(|row| row.iter().map(|cell| format!("{}", cell)).collect())
+            .enumerate()
+            .map(|(i, row)| {
+                row.iter()
+                    .enumerate()
+                    .map(|(j, cell)| {
+                        if highlight.errors.contains(&(i, j)) {
+                            cell.glyph().white().on_red().to_string()
+                        } else if highlight.cells.contains(&(i, j)) {
+                            cell.glyph().black().on_yellow().to_string()
+                        } else if highlight.ambiguous.contains(&(i, j)) {
+                            cell.glyph().black().on_magenta().to_string()
+                        } else if highlight
+                            .cursor
+                            .is_some_and(|(row, col)| row == i || col == j)
+                        {
+                            cell.glyph().black().on_cyan().to_string()
+                        } else if let Square::Filled(color @ 1..) = cell {
+                            cell.glyph()
+                                .with(term_color(&self.palette[*color as usize]))
+                                .on_white()
+                                .to_string()
+                        } else {
+                            format!("{}", cell)
+                        }
+                    })
+                    .collect()
+            })
             .collect()
     }
 
-    pub fn render_all(&mut self) -> Vec<Vec<String>> {
+    /// Number of separators render_all puts before the i-th cell of a line
+    fn separators_before(&self, i: usize) -> usize {
+        match self.separator_every {
+            Some(every) if every > 0 => i / every,
+            _ => 0,
+        }
+    }
+
+    /// Where on screen the cell at (row, col) was drawn by the last render_all, as (x, y)
+    pub fn screen_pos(&self, row: usize, col: usize) -> (usize, usize) {
+        let (grid_row, grid_col) = self.grid_pos.unwrap_or((0, 0));
+        (
+            grid_col + col + self.separators_before(col),
+            grid_row + row + self.separators_before(row),
+        )
+    }
+
+    /// The (row, col) of the cell drawn at screen position (x, y) by the last render_all, if any.
+    /// None for anything outside the grid, including the separators
+    pub fn cell_at(&self, x: usize, y: usize) -> Option<(usize, usize)> {
+        /// Undoes separators_before for one axis. offset is the distance from the grid's edge
+        fn unseparate(offset: usize, every: Option<usize>) -> Option<usize> {
+            match every {
+                Some(every) if every > 0 => match offset % (every + 1) {
+                    // Landed right on a separator
+                    i if i == every => None,
+                    i => Some(offset / (every + 1) * every + i),
+                },
+                _ => Some(offset),
+            }
+        }
+        let (grid_row, grid_col) = self.grid_pos?;
+        let row = unseparate(y.checked_sub(grid_row)?, self.separator_every)?;
+        let col = unseparate(x.checked_sub(grid_col)?, self.separator_every)?;
+        (row < self.rows && col < self.cols).then_some((row, col))
+    }
+
+    /// Splices separator lines into a finished render, between every separator_every cells of the
+    /// grid. They run all the way through the clue bars too so hints line up with their blocks
+    fn add_separators(&self, rendered_game: &mut Vec<Vec<String>>) {
+        let Some(every) = self.separator_every.filter(|every| *every > 0) else {
+            return;
+        };
+        let (grid_row, grid_col) = self.grid_pos.expect("grid has been placed");
+
+        // Separators go in the clue bars too, which are grey instead of white
+        fn style(line: &str, in_bar: bool) -> String {
+            match in_bar {
+                true => line.blue().on_grey().to_string(),
+                false => line.blue().on_white().to_string(),
+            }
+        }
+
+        // Backwards so inserting doesn't shift the columns that are still to do
+        for col in (1..self.cols).rev().filter(|col| col % every == 0) {
+            for (y, line) in rendered_game.iter_mut().enumerate() {
+                line.insert(grid_col + col, style("┃", y < grid_row));
+            }
+        }
+        let vertical_xs: Vec<usize> = (1..self.cols)
+            .filter(|col| col % every == 0)
+            .map(|col| self.screen_pos(0, col).0 - 1)
+            .collect();
+        let width = rendered_game.first().map_or(0, |line| line.len());
+        let horizontal: Vec<String> = (0..width)
+            .map(|x| match vertical_xs.contains(&x) {
+                true => style("╋", false),
+                false => style("━", x < grid_col),
+            })
+            .collect();
+
+        for row in (1..self.rows).rev().filter(|row| row % every == 0) {
+            rendered_game.insert(grid_row + row, horizontal.clone());
+        }
+    }
+
+    /// clue_statuses for every row (or col) to go with shown_hints. The groups of a mega clue
+    /// all go by how its pair of lines is doing as a whole
+    fn shown_statuses(&self, along_col: bool) -> Vec<Vec<SegmentStatus>> {
+        let job = |i| match along_col {
+            false => Job::Row(i),
+            true => Job::Col(i),
+        };
+        let lines = match along_col {
+            false => self.rows,
+            true => self.cols,
+        };
+        let mut statuses: Vec<Vec<SegmentStatus>> = (0..lines)
+            .map(|i| {
+                let (hint, line) = self.get_line(job(i));
+                self.clue_statuses(&hint, &line)
+            })
+            .collect();
+        for mega in self.megas_along(along_col) {
+            let (a, b) = self.get_pair(job(mega.first));
+            let status = match Game::refine_pair(&a, &b, &mega.groups) {
+                None => SegmentStatus::Violated,
+                Some(_) if !a.contains(&Square::Unknown) && !b.contains(&Square::Unknown) => {
+                    SegmentStatus::Done
+                }
+                Some(_) => SegmentStatus::Open,
+            };
+            statuses[mega.first] = vec![status; mega.groups.len()];
+            statuses[mega.first + 1] = vec![];
+        }
+        statuses
+    }
+
+    pub fn render_all(&mut self, highlight: &Highlight) -> Vec<Vec<String>> {
         #[inline]
         fn transpose(matrix: Vec<Vec<String>>) -> Vec<Vec<String>> {
             let mut transposed = vec![vec!["".to_string(); matrix.len()]; matrix[0].len()];
@@ -119,8 +357,35 @@ impl Game {
             transposed
         }
 
-        let rendered_row_hints = Self::render_hints(&self.row_hints);
-        let rendered_col_hints = transpose(Self::render_hints(&self.col_hints));
+        let (highlighted_row, highlighted_col) = match highlight.job {
+            Some(Job::Row(i)) => (Some(i), None),
+            Some(Job::Col(i)) => (None, Some(i)),
+            None => (None, None),
+        };
+        let (row_statuses, col_statuses) = match highlight.clue_status {
+            true => (self.shown_statuses(false), self.shown_statuses(true)),
+            false => (vec![], vec![]),
+        };
+        let firsts = |along_col| -> Vec<usize> {
+            let megas = self.megas_along(along_col);
+            megas.iter().map(|mega| mega.first).collect()
+        };
+        let rendered_row_hints = Self::render_hints(
+            &self.shown_hints(false),
+            &row_statuses,
+            highlighted_row,
+            highlight.cursor.map(|(row, _)| row),
+            &self.palette,
+            &firsts(false),
+        );
+        let rendered_col_hints = transpose(Self::render_hints(
+            &self.shown_hints(true),
+            &col_statuses,
+            highlighted_col,
+            highlight.cursor.map(|(_, col)| col),
+            &self.palette,
+            &firsts(true),
+        ));
 
         let col_hints_pos = (0usize, rendered_row_hints[0].len()); // Places top bar of hints just to
                                                                    // the right of where the side bar ends laterally
@@ -151,12 +416,18 @@ impl Game {
 
         self.grid_pos = Some((row_hints_pos.0, col_hints_pos.1));
         place_object(
-            self.render_grid(),
+            self.render_grid(highlight),
             self.grid_pos.unwrap().0,
             self.grid_pos.unwrap().1,
             &mut rendered_game,
         )
         .unwrap();
+        // Wrapped puzzles get marked in the empty corner, since nothing else about them looks
+        // any different
+        if self.wrap {
+            rendered_game[0][0] = "↻".to_string();
+        }
+        self.add_separators(&mut rendered_game);
 
         rendered_game
     }
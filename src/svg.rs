@@ -0,0 +1,207 @@
+use std::fmt::Write;
+
+use crate::game::{Color, Corner, Game, Square};
+
+/// Size of one cell in px. Clues get a cell-sized slot per segment too
+const CELL: usize = 20;
+
+impl Game {
+    /// SVG fill for a palette color
+    fn fill(&self, color: Color) -> String {
+        let (r, g, b) = self.palette[color as usize].rgb;
+        format!("#{:02x}{:02x}{:02x}", r, g, b)
+    }
+
+    /// Renders the puzzle as a printable SVG: clues plus the grid with thicker guide lines every
+    /// separator_every cells, same as the terminal. With show_grid the squares in grid get drawn
+    /// too (filled as black, empty as a small cross) so solved or half solved games can be
+    /// printed, otherwise the grid is left blank.
+    pub fn to_svg(&self, show_grid: bool) -> String {
+        self.to_svg_marked(show_grid, &[])
+    }
+
+    /// Same as to_svg, with the squares at marked shaded in so they stand out. Used to point out
+    /// squares the hints don't pin down
+    pub fn to_svg_marked(&self, show_grid: bool, marked: &[(usize, usize)]) -> String {
+        let (row_hints, col_hints) = (self.shown_hints(false), self.shown_hints(true));
+        let max_row_segments = row_hints.iter().map(|hint| hint.len()).max().unwrap_or(0);
+        let max_col_segments = col_hints.iter().map(|hint| hint.len()).max().unwrap_or(0);
+        // Top left of the grid itself
+        let left = max_row_segments.max(1) * CELL;
+        let top = max_col_segments.max(1) * CELL;
+        let width = left + self.cols * CELL;
+        let height = top + self.rows * CELL;
+
+        // Writing to a String can't fail so all the unwraps below are fine
+        let mut svg = String::new();
+        writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="-1 -1 {w} {h}">"#,
+            w = width + 2,
+            h = height + 2,
+        )
+        .unwrap();
+        writeln!(
+            svg,
+            r#"<rect x="-1" y="-1" width="{}" height="{}" fill="white"/>"#,
+            width + 2,
+            height + 2
+        )
+        .unwrap();
+
+        // Mega clues get a box right across both their lines' clue slots to join them up
+        for mega in &self.mega_rows {
+            writeln!(
+                svg,
+                r##"<rect x="0" y="{}" width="{left}" height="{}" rx="4" fill="#dde6ff"/>"##,
+                top + mega.first * CELL,
+                2 * CELL,
+            )
+            .unwrap();
+        }
+        for mega in &self.mega_cols {
+            writeln!(
+                svg,
+                r##"<rect x="{}" y="0" width="{}" height="{top}" rx="4" fill="#dde6ff"/>"##,
+                left + mega.first * CELL,
+                2 * CELL,
+            )
+            .unwrap();
+        }
+
+        // Clues, right aligned against the grid like the terminal version
+        writeln!(
+            svg,
+            r#"<g font-family="sans-serif" font-size="{}" text-anchor="middle" dominant-baseline="central">"#,
+            CELL * 3 / 5
+        )
+        .unwrap();
+        // Wrapped puzzles get marked in the corner
+        if self.wrap {
+            writeln!(svg, r#"<text x="{0}" y="{0}">↻</text>"#, CELL / 2).unwrap();
+        }
+        for (row, hint) in row_hints.iter().enumerate() {
+            let first = left / CELL - hint.len();
+            for (i, seg) in hint.iter().enumerate() {
+                writeln!(
+                    svg,
+                    r#"<text x="{}" y="{}" fill="{}">{}</text>"#,
+                    (first + i) * CELL + CELL / 2,
+                    top + row * CELL + CELL / 2,
+                    self.fill(seg.color),
+                    seg
+                )
+                .unwrap();
+            }
+        }
+        for (col, hint) in col_hints.iter().enumerate() {
+            let first = top / CELL - hint.len();
+            for (i, seg) in hint.iter().enumerate() {
+                writeln!(
+                    svg,
+                    r#"<text x="{}" y="{}" fill="{}">{}</text>"#,
+                    left + col * CELL + CELL / 2,
+                    (first + i) * CELL + CELL / 2,
+                    self.fill(seg.color),
+                    seg
+                )
+                .unwrap();
+            }
+        }
+        writeln!(svg, "</g>").unwrap();
+
+        // Shading goes under everything else in the grid so marks on top stay visible
+        for (row, col) in marked {
+            writeln!(
+                svg,
+                r#"<rect x="{}" y="{}" width="{CELL}" height="{CELL}" fill="magenta" fill-opacity="0.4"/>"#,
+                left + col * CELL,
+                top + row * CELL,
+            )
+            .unwrap();
+        }
+
+        if show_grid {
+            for (row, line) in self.grid.iter().enumerate() {
+                for (col, square) in line.iter().enumerate() {
+                    let (x, y) = (left + col * CELL, top + row * CELL);
+                    match square {
+                        Square::Filled(color) => writeln!(
+                            svg,
+                            r#"<rect x="{x}" y="{y}" width="{CELL}" height="{CELL}" fill="{}"/>"#,
+                            self.fill(*color),
+                        )
+                        .unwrap(),
+                        Square::Triangle(corner) => {
+                            // The 3 corners of the cell the triangle keeps, which is every one but
+                            // the corner opposite
+                            let (l, r, t, b) = (x, x + CELL, y, y + CELL);
+                            let points = match corner {
+                                Corner::TopLeft => [(l, t), (r, t), (l, b)],
+                                Corner::TopRight => [(l, t), (r, t), (r, b)],
+                                Corner::BottomLeft => [(l, t), (r, b), (l, b)],
+                                Corner::BottomRight => [(r, t), (r, b), (l, b)],
+                            }
+                            .map(|(x, y)| format!("{x},{y}"));
+                            writeln!(
+                                svg,
+                                r#"<polygon points="{}" fill="{}"/>"#,
+                                points.join(" "),
+                                self.fill(0),
+                            )
+                            .unwrap()
+                        }
+                        Square::Empty => {
+                            let pad = CELL / 4;
+                            writeln!(
+                                svg,
+                                r#"<path d="M{} {}L{} {}M{} {}L{} {}" stroke="grey" stroke-width="1.5"/>"#,
+                                x + pad,
+                                y + pad,
+                                x + CELL - pad,
+                                y + CELL - pad,
+                                x + CELL - pad,
+                                y + pad,
+                                x + pad,
+                                y + CELL - pad,
+                            )
+                            .unwrap()
+                        }
+                        Square::Unknown => {}
+                    }
+                }
+            }
+        }
+
+        // Grid lines go last so they sit on top of the filled squares. Lines on a guide or on the
+        // outside edge are thick, the rest are thin
+        let every = self.separator_every.filter(|every| *every > 0);
+        let line_width = |i: usize, count: usize| match i == 0
+            || i == count
+            || every.is_some_and(|every| i.is_multiple_of(every))
+        {
+            true => 2,
+            false => 1,
+        };
+        for row in 0..=self.rows {
+            let y = top + row * CELL;
+            writeln!(
+                svg,
+                r#"<line x1="0" y1="{y}" x2="{width}" y2="{y}" stroke="black" stroke-width="{}"/>"#,
+                line_width(row, self.rows)
+            )
+            .unwrap();
+        }
+        for col in 0..=self.cols {
+            let x = left + col * CELL;
+            writeln!(
+                svg,
+                r#"<line x1="{x}" y1="0" x2="{x}" y2="{height}" stroke="black" stroke-width="{}"/>"#,
+                line_width(col, self.cols)
+            )
+            .unwrap();
+        }
+        writeln!(svg, "</svg>").unwrap();
+        svg
+    }
+}
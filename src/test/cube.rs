@@ -0,0 +1,81 @@
+use crate::{
+    cube::{Cube, CubeClue, Grouping},
+    game::{Glyphs, Outcome, Square},
+    test::black,
+};
+
+// z = 1  z = 2
+//   oo     xx
+//   ox     ox
+const CUBE: &str = "cube 2 2 2\nx\n2 1\n0 1\ny\n2 1\n1 0\nz\n1 1\n2 0\n";
+
+fn line(line_str: &str) -> Vec<Square> {
+    line_str
+        .chars()
+        .map(|c| match c {
+            'o' => Square::Filled(0),
+            'x' => Square::Empty,
+            _ => Square::Unknown,
+        })
+        .collect()
+}
+
+#[test]
+pub fn clues_stand_for_every_split() {
+    let circled = CubeClue {
+        count: 3,
+        grouping: Grouping::Circled,
+    };
+    assert_eq!(circled.hints(5), vec![black(&[1, 2]), black(&[2, 1])]);
+    let squared = CubeClue {
+        count: 4,
+        grouping: Grouping::Squared,
+    };
+    assert_eq!(
+        squared.hints(6),
+        vec![black(&[1, 1, 2]), black(&[1, 2, 1]), black(&[2, 1, 1])]
+    );
+    // 6 is too short for 4 runs of 1 with gaps, 7 just fits them
+    assert_eq!(squared.hints(7).len(), 4);
+    let empty = CubeClue {
+        count: 0,
+        grouping: Grouping::Plain,
+    };
+    assert_eq!(empty.hints(3), vec![black(&[])]);
+}
+
+#[test]
+pub fn lines_keep_what_every_split_agrees_on() {
+    let circled = CubeClue {
+        count: 3,
+        grouping: Grouping::Circled,
+    };
+    let (refined, _, changed) = Cube::refine_line(&line("____"), Some(&circled)).unwrap();
+    assert_eq!(refined, line("o__o"));
+    assert!(changed);
+    assert!(Cube::refine_line(&line("ooo_"), Some(&circled)).is_none());
+    assert_eq!(
+        Cube::refine_line(&line("o__"), None).unwrap().0,
+        line("o__")
+    );
+}
+
+#[test]
+pub fn cube_solves_and_prints_by_slice() {
+    let mut cube = Cube::from_clues_str(CUBE).unwrap();
+    assert_eq!(cube.solve(), Outcome::Solved);
+    assert_eq!(
+        cube.to_text(&Glyphs::default()),
+        "slice 1\n  2 1\n2 # #\n1 # x\n\nslice 2\n  1 0\n0 x x\n1 # x\n\nthrough the slices\n  1 1\n  2 0"
+    );
+}
+
+#[test]
+pub fn rejects_clues_that_dont_fit() {
+    // Circled needs at least 2, and the clue count has to match the size
+    assert!(Cube::from_clues_str("cube 1 1 1\nx\n(1)\ny\n1\nz\n1\n").is_err());
+    assert!(Cube::from_clues_str("cube 3 1 1\nx\n[3]\ny\n* * *\nz\n* * *\n").is_err());
+    assert!(Cube::from_clues_str("cube 1 1 1\nx\n1 1\ny\n1\nz\n1\n").is_err());
+    assert!(Cube::from_clues_str("cube 1 1\nx\n1\n").is_err());
+    assert!(Cube::from_clues_str("cube 3 1 1\nx\n(2)\ny\n* * *\nz\n* * *\n").is_ok());
+}
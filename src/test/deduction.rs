@@ -0,0 +1,67 @@
+use crate::{
+    game::{Game, Job, Square},
+    test::black,
+};
+
+fn game_from_rows(col_hints: Vec<Vec<u32>>, row_hints: Vec<Vec<u32>>, rows: &[&str]) -> Game {
+    let mut game = Game::new(col_hints, row_hints).unwrap();
+    for (i, row) in rows.iter().enumerate() {
+        game.set_row(
+            i,
+            row.chars()
+                .map(|c| match c {
+                    'o' => Square::Filled(0),
+                    'x' => Square::Empty,
+                    _ => Square::Unknown,
+                })
+                .collect(),
+        );
+    }
+    game
+}
+
+#[test]
+pub fn picks_line_with_fewest_unknowns() {
+    // Row 1 is a full line of 3 so it's a gimme, but col 0 only has 1 unknown left
+    let game = game_from_rows(
+        vec![vec![2], vec![1], vec![2]],
+        vec![vec![1], vec![3], vec![1]],
+        &["o__", "___", "x__"],
+    );
+    let deduction = game.next_deduction().unwrap().unwrap();
+    assert_eq!(deduction.job, Job::Col(0));
+    assert_eq!(deduction.cells, vec![(1, Square::Filled(0))]);
+}
+
+#[test]
+pub fn flags_contradicting_line() {
+    let game = game_from_rows(
+        vec![vec![1], vec![1], vec![1]],
+        vec![vec![1], vec![1], vec![1]],
+        &["oo_", "___", "___"],
+    );
+    assert_eq!(game.next_deduction().unwrap_err(), Job::Row(0));
+}
+
+#[test]
+pub fn nothing_left_when_solved() {
+    let game = game_from_rows(
+        vec![vec![1], vec![1]],
+        vec![vec![1], vec![1]],
+        &["ox", "xo"],
+    );
+    assert!(game.next_deduction().unwrap().is_none());
+}
+
+#[test]
+pub fn explains_overlap_and_gaps() {
+    let reasons = Game::explain_line(
+        &black(&[4]),
+        &vec![Square::Unknown; 6],
+        &[(2, Square::Filled(0)), (3, Square::Filled(0))],
+    );
+    assert_eq!(
+        reasons,
+        vec!["The 4 pushed as far left and as far right as it goes still covers 3, 4"]
+    );
+}
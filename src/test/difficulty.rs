@@ -0,0 +1,113 @@
+use crate::{
+    difficulty::{Category, Difficulty, Tier},
+    game::{Game, SolveStats},
+    generate::{generate, GeneratorOptions},
+};
+
+#[test]
+pub fn full_square_is_easy() {
+    let game = Game::new(vec![vec![3]; 3], vec![vec![3]; 3]).unwrap();
+    let difficulty = game.rate().unwrap();
+    assert_eq!(difficulty.category, Category::Easy);
+    assert_eq!(difficulty.stats.probes, 0);
+    assert_eq!(difficulty.stats.guesses, 0);
+}
+
+#[test]
+pub fn ambiguous_has_no_rating() {
+    let game = Game::new(vec![vec![1], vec![1]], vec![vec![1], vec![1]]).unwrap();
+    assert_eq!(game.rate(), None);
+}
+
+#[test]
+pub fn category_comes_before_score() {
+    let long_medium = Difficulty::from_stats(SolveStats {
+        passes: 8,
+        lines_refined: 200,
+        ..Default::default()
+    });
+    let short_hard = Difficulty::from_stats(SolveStats {
+        passes: 3,
+        lines_refined: 20,
+        probes: 1,
+        ..Default::default()
+    });
+    assert_eq!(long_medium.category, Category::Medium);
+    assert_eq!(short_hard.category, Category::Hard);
+    assert!(long_medium.score < short_hard.score);
+}
+
+#[test]
+pub fn category_goes_by_hardest_trick() {
+    let category = |stats| Difficulty::from_stats(stats).category;
+    let line = SolveStats {
+        passes: 2,
+        lines_refined: 10,
+        ..Default::default()
+    };
+    assert_eq!(category(line.clone()), Category::Easy);
+    assert_eq!(
+        category(SolveStats {
+            passes: 20,
+            ..line.clone()
+        }),
+        Category::Medium
+    );
+    assert_eq!(
+        category(SolveStats {
+            probes: 1,
+            ..line.clone()
+        }),
+        Category::Hard
+    );
+    assert_eq!(
+        category(SolveStats {
+            probes: 3,
+            guesses: 2,
+            max_depth: 1,
+            ..line
+        }),
+        Category::Expert
+    );
+}
+
+#[test]
+pub fn needs_more_than_line_logic_when_line_logic_is_stuck() {
+    // seed 22 is one that line logic can't finish
+    for seed in 0..30 {
+        let game = generate(&GeneratorOptions {
+            seed,
+            rows: 6,
+            cols: 6,
+            search: true,
+            ..Default::default()
+        })
+        .unwrap();
+        let difficulty = game.rate().unwrap();
+        match game.line_solve() {
+            Some(_) => assert!(difficulty.category <= Category::Medium),
+            None => assert!(difficulty.category >= Category::Hard),
+        }
+    }
+}
+
+#[test]
+pub fn tiers() {
+    let full = Game::new(vec![vec![3]; 3], vec![vec![3]; 3]).unwrap();
+    assert_eq!(full.tier(), Some(Tier::Overlap));
+    let diagonals = Game::new(vec![vec![1], vec![1]], vec![vec![1], vec![1]]).unwrap();
+    assert_eq!(diagonals.tier(), None);
+}
+
+#[test]
+pub fn tier_names_round_trip() {
+    for tier in [
+        Tier::Overlap,
+        Tier::LineSolver,
+        Tier::Probing,
+        Tier::Backtracking,
+    ] {
+        assert_eq!(Tier::from_name(&tier.to_string()), Some(tier));
+    }
+    assert_eq!(Tier::from_name("nope"), None);
+}
@@ -0,0 +1,138 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::{
+    edit::Edit,
+    game::{Game, Square},
+    test::black_hints,
+};
+
+fn press(edit: &mut Edit, keys: &str) {
+    for c in keys.chars() {
+        edit.handle_key(KeyEvent::from(KeyCode::Char(c)));
+    }
+}
+
+#[test]
+pub fn hints_follow_the_picture() {
+    let mut edit = Edit::blank(2, 3, String::new());
+    assert_eq!(edit.game.row_hints, black_hints(vec![vec![], vec![]]));
+    press(&mut edit, " l jl ");
+    assert_eq!(edit.game.row_hints, black_hints(vec![vec![2], vec![1]]));
+    assert_eq!(
+        edit.game.col_hints,
+        black_hints(vec![vec![1], vec![1], vec![1]])
+    );
+    // Filling again erases
+    press(&mut edit, " ");
+    assert_eq!(edit.game.row_hints, black_hints(vec![vec![2], vec![]]));
+}
+
+#[test]
+pub fn shift_move_paints_along() {
+    let mut edit = Edit::blank(1, 4, String::new());
+    press(&mut edit, " ");
+    for _ in 0..2 {
+        edit.handle_key(KeyEvent::new(KeyCode::Right, KeyModifiers::SHIFT));
+    }
+    assert_eq!(edit.game.row_hints, black_hints(vec![vec![3]]));
+    press(&mut edit, "x");
+    edit.handle_key(KeyEvent::new(KeyCode::Left, KeyModifiers::SHIFT));
+    assert_eq!(edit.game.row_hints, black_hints(vec![vec![1]]));
+}
+
+#[test]
+pub fn ambiguity_is_tracked() {
+    let mut edit = Edit::blank(2, 2, String::new());
+    assert!(edit.ambiguous.is_empty());
+    // A diagonal has two solutions
+    press(&mut edit, " jl ");
+    assert_eq!(edit.ambiguous.len(), 4);
+    press(&mut edit, "h ");
+    assert!(edit.ambiguous.is_empty());
+}
+
+#[test]
+pub fn starts_from_the_solution() {
+    let game = Game::from_solution(vec![
+        vec![Square::Filled(0), Square::Filled(0), Square::Empty],
+        vec![Square::Empty, Square::Empty, Square::Filled(0)],
+    ]);
+    let edit = Edit::new(game.clone(), String::new());
+    assert_eq!(edit.puzzle().row_hints, game.row_hints);
+    assert_eq!(edit.game.grid[1][0], Square::Unknown);
+}
+
+#[test]
+pub fn saves_by_extension() {
+    let dir = std::env::temp_dir();
+    let mut edit = Edit::blank(2, 3, String::new());
+    press(&mut edit, " l jl ");
+    let text = dir.join("nonagram_edit_test.txt");
+    edit.path = text.to_string_lossy().into_owned();
+    edit.save().unwrap();
+    let saved = Game::from_hints_str(&std::fs::read_to_string(&text).unwrap()).unwrap();
+    assert_eq!(saved.row_hints, edit.game.row_hints);
+    assert_eq!(saved.solution, edit.puzzle().solution);
+
+    let svg = dir.join("nonagram_edit_test.svg");
+    edit.path = svg.to_string_lossy().into_owned();
+    edit.save().unwrap();
+    assert!(std::fs::read_to_string(&svg).unwrap().starts_with("<svg"));
+}
+
+#[test]
+pub fn opening_recovers_the_picture() {
+    // Needs guessing, so line logic alone would come up blank
+    let mut game = crate::generate::generate(&crate::generate::GeneratorOptions {
+        rows: 8,
+        cols: 8,
+        tier: Some(crate::difficulty::Tier::Probing),
+        ..Default::default()
+    })
+    .unwrap();
+    let solution = game.solution.take();
+    let edit = Edit::open(game, String::new()).unwrap();
+    assert_eq!(edit.puzzle().solution, solution);
+    assert!(edit.message.is_empty());
+
+    // Either diagonal will do, but it gets said
+    let diagonals = Game::from_hints_str("1\n1\n\n1\n1\n").unwrap();
+    let edit = Edit::open(diagonals, String::new()).unwrap();
+    assert_eq!(edit.game.row_hints, black_hints(vec![vec![1], vec![1]]));
+    assert!(!edit.message.is_empty());
+
+    assert!(Edit::open(
+        Game::from_hints_str("2\n0\n\n1\n0\n").unwrap(),
+        String::new()
+    )
+    .is_err());
+}
+
+#[test]
+pub fn opening_says_mega_clues_go() {
+    let game = Game::from_hints_str("3 1\n^\n1\n\n1 1\n1\n1\n1\n").unwrap();
+    let edit = Edit::open(game, String::new()).unwrap();
+    assert!(edit.game.mega_rows.is_empty());
+    assert_eq!(edit.message.len(), 1);
+}
+
+#[test]
+pub fn searching_is_only_on_request() {
+    let game = crate::generate::generate(&crate::generate::GeneratorOptions {
+        rows: 8,
+        cols: 8,
+        tier: Some(crate::difficulty::Tier::Probing),
+        ..Default::default()
+    })
+    .unwrap();
+    let mut edit = Edit::new(game, String::new());
+    // Line logic gets stuck, but there's only the one picture
+    assert!(!edit.line_solvable);
+    assert!(!edit.ambiguous.is_empty());
+    press(&mut edit, "u");
+    assert!(edit.searched);
+    assert!(edit.ambiguous.is_empty());
+    // Any change goes back to just line logic
+    press(&mut edit, " ");
+    assert!(!edit.searched);
+}
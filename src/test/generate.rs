@@ -0,0 +1,96 @@
+use crate::{
+    difficulty::Tier,
+    game::{Game, Solver, Square},
+    generate::{ambiguous_cells, generate, GeneratorOptions},
+};
+
+#[test]
+pub fn same_seed_same_puzzle() {
+    let options = GeneratorOptions {
+        seed: 42,
+        ..Default::default()
+    };
+    assert_eq!(
+        generate(&options).unwrap().hints_to_string(),
+        generate(&options).unwrap().hints_to_string()
+    );
+    let other = GeneratorOptions {
+        seed: 43,
+        ..Default::default()
+    };
+    assert_ne!(
+        generate(&options).unwrap().hints_to_string(),
+        generate(&other).unwrap().hints_to_string()
+    );
+}
+
+#[test]
+pub fn line_only_puzzles_line_solve() {
+    for seed in 0..5 {
+        let game = generate(&GeneratorOptions {
+            seed,
+            rows: 8,
+            cols: 12,
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(game.line_solve(), game.solution);
+    }
+}
+
+#[test]
+pub fn search_puzzles_are_unique() {
+    for seed in 0..5 {
+        let game = generate(&GeneratorOptions {
+            seed,
+            search: true,
+            ..Default::default()
+        })
+        .unwrap();
+        let mut blank = game.clone();
+        blank.grid = vec![vec![Square::Unknown; game.cols]; game.rows];
+        let solutions = Solver::new(blank).search(2);
+        assert_eq!(solutions.len(), 1);
+        assert_eq!(Some(&solutions[0]), game.solution.as_ref());
+    }
+}
+
+#[test]
+pub fn search_finds_both_diagonals() {
+    let game = Game::new(vec![vec![1], vec![1]], vec![vec![1], vec![1]]).unwrap();
+    let solutions = Solver::new(game.clone()).search(10);
+    assert_eq!(solutions.len(), 2);
+    assert_eq!(ambiguous_cells(&game, true).len(), 4);
+    assert_eq!(ambiguous_cells(&game, false).len(), 4);
+}
+
+#[test]
+pub fn puzzles_land_in_requested_tier() {
+    for tier in [Tier::Overlap, Tier::LineSolver, Tier::Probing] {
+        for seed in 0..3 {
+            let game = generate(&GeneratorOptions {
+                seed,
+                rows: 8,
+                cols: 8,
+                tier: Some(tier),
+                ..Default::default()
+            })
+            .unwrap();
+            assert_eq!(game.tier(), Some(tier));
+        }
+    }
+}
+
+#[test]
+pub fn gives_up_on_tiers_too_big_for_the_grid() {
+    // Nothing this small ever needs probing
+    let game = generate(&GeneratorOptions {
+        rows: 2,
+        cols: 2,
+        seed: 1,
+        tier: Some(Tier::Probing),
+        max_pictures: 50,
+        ..Default::default()
+    });
+    assert!(game.is_none());
+}
@@ -0,0 +1,48 @@
+use crate::{
+    game::{Glyphs, Outcome},
+    hex::Hex,
+};
+
+//  o o
+// o x o
+//  x o
+const HEX: &str = "hex 1\n2\n1 1\n1\n\n1\n1 1\n2\n\n2\n1\n2\n";
+
+#[test]
+pub fn every_cell_is_on_3_lines() {
+    let hex = Hex::from_hints_str(HEX).unwrap();
+    let lines = hex.topology().lines;
+    assert_eq!(lines.len(), 9);
+    for cell in 0..hex.cells.len() {
+        assert_eq!(
+            lines
+                .iter()
+                .filter(|line| line.cells.contains(&cell))
+                .count(),
+            3
+        );
+    }
+    // Middle row, then the line down to the right through the middle, then down to the left
+    assert_eq!(lines[1].cells, vec![2, 3, 4]);
+    assert_eq!(lines[4].cells, vec![0, 3, 6]);
+    assert_eq!(lines[7].cells, vec![1, 3, 5]);
+}
+
+#[test]
+pub fn hex_solves_and_prints() {
+    let mut hex = Hex::from_hints_str(HEX).unwrap();
+    assert_eq!(hex.solve(), Outcome::Solved);
+    assert_eq!(
+        hex.to_text(&Glyphs::default()),
+        "  2  # #\n1 1 # x #\n  1  x #\n↘ 1 | 1 1 | 2\n↙ 2 | 1 | 2"
+    );
+}
+
+#[test]
+pub fn hex_needs_hints_for_every_line() {
+    assert!(Hex::from_hints_str("hex 1\n2\n1 1\n1\n\n1\n1 1\n2\n").is_err());
+    assert!(Hex::from_hints_str("hex 0\n1\n\n1\n\n0\n").is_ok());
+    assert!(Hex::from_hints_str("hex 0\n◢1\n\n1\n\n1\n").is_err());
+    // The middle row is 3 long but the top only 2
+    assert!(Hex::from_hints_str("hex 1\n3\n1\n1\n\n1\n1\n1\n\n1\n1\n1\n").is_err());
+}
@@ -0,0 +1,100 @@
+use crate::{
+    game::{Game, Square},
+    import::{Binarize, Image, ImportError},
+    test::black_hints,
+};
+
+#[test]
+pub fn reads_ascii_pbm() {
+    let image = Image::decode(b"P1\n# a comment\n3 2\n1 0 1\n0 1 0\n").unwrap();
+    assert_eq!((image.width, image.height), (3, 2));
+    assert_eq!(image.pixels, vec![0, 255, 0, 255, 0, 255]);
+}
+
+#[test]
+pub fn reads_binary_pgm() {
+    let image = Image::decode(b"P5 2 1 15\n\x00\x0f").unwrap();
+    assert_eq!(image.pixels, vec![0, 255]);
+}
+
+#[test]
+pub fn reads_back_exported_png() {
+    let mut game = Game::new(vec![vec![1], vec![1]], vec![vec![1], vec![1]]).unwrap();
+    game.grid = vec![
+        vec![Square::Filled(0), Square::Empty],
+        vec![Square::Empty, Square::Filled(0)],
+    ];
+    let image = Image::decode(&game.to_png(3).unwrap()).unwrap();
+    let imported = Game::from_image(&image, 2, 2, Binarize::Threshold(128));
+    assert_eq!(imported.solution, Some(game.grid));
+    assert_eq!(imported.row_hints, black_hints(vec![vec![1], vec![1]]));
+}
+
+#[test]
+pub fn shrinking_averages_blocks() {
+    let image = Image {
+        width: 4,
+        height: 2,
+        pixels: vec![0, 0, 255, 255, 0, 255, 255, 255],
+    };
+    let small = image.resize(2, 1);
+    assert_eq!(small.pixels, vec![63, 255]);
+    assert_eq!(
+        small.threshold(128),
+        vec![vec![Square::Filled(0), Square::Empty]]
+    );
+}
+
+#[test]
+pub fn dithering_keeps_shading() {
+    let image = Image {
+        width: 8,
+        height: 8,
+        pixels: vec![128; 64],
+    };
+    let filled = image
+        .dither()
+        .iter()
+        .flatten()
+        .filter(|s| **s == Square::Filled(0))
+        .count();
+    // A flat mid grey should come out about half filled instead of all one way
+    assert!((24..=40).contains(&filled), "{}", filled);
+}
+
+#[test]
+pub fn rejects_other_formats() {
+    assert!(Image::decode(b"GIF89a").is_err());
+}
+
+#[test]
+pub fn rejects_empty_images() {
+    assert!(matches!(
+        Image::decode(b"P2\n0 0\n255\n"),
+        Err(ImportError::Empty)
+    ));
+    assert!(matches!(
+        Image::decode(b"P1\n3 0\n"),
+        Err(ImportError::Empty)
+    ));
+}
+
+#[test]
+pub fn reads_ascii_pbm_without_spaces() {
+    let image = Image::decode(b"P1\n4 2\n0110\n1001\n").unwrap();
+    assert_eq!(image.pixels, vec![255, 0, 0, 255, 0, 255, 255, 0]);
+}
+
+#[test]
+pub fn rejects_sizes_that_overflow() {
+    let huge = format!("P4\n{} {}\n", usize::MAX, 2);
+    assert!(matches!(
+        Image::decode(huge.as_bytes()),
+        Err(ImportError::BadHeader)
+    ));
+    let huge = format!("P2\n{} {}\n255\n", usize::MAX / 2, 3);
+    assert!(matches!(
+        Image::decode(huge.as_bytes()),
+        Err(ImportError::BadHeader)
+    ));
+}
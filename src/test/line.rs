@@ -1,13 +1,20 @@
-use crate::game::{Game, SegmentPlacement, Square};
+use crate::{
+    game::{Corner, Game, Hint, PaletteColor, Segment, SegmentPlacement, SegmentStatus, Square},
+    parse::parse_hint,
+    test::black,
+};
 
+/// o is black, and r and g are colors 1 and 2 for color tests. Triangles are their glyphs
 fn line_from_str(line_str: &str) -> Vec<Square> {
     line_str
         .chars()
         .map(|c| match c {
             '_' => Square::Unknown,
             'x' => Square::Empty,
-            'o' => Square::Filled,
-            _ => panic!("ruh roh"),
+            'o' => Square::Filled(0),
+            'r' => Square::Filled(1),
+            'g' => Square::Filled(2),
+            c => Square::Triangle(Corner::from_glyph(c).expect("ruh roh")),
         })
         .collect()
 }
@@ -26,7 +33,7 @@ pub fn str_from_placements(placements: Option<Vec<SegmentPlacement>>) -> Option<
 
 pub fn test_line(hint: &[u32], line: &str, expected_line: Option<&str>) {
     assert_eq!(
-        str_from_placements(Game::place_all_left(hint, &line_from_str(line))),
+        str_from_placements(Game::place_all_left(&black(hint), &line_from_str(line))),
         expected_line.map(|s| s.to_owned())
     )
 }
@@ -37,10 +44,7 @@ macro_rules! left_line_tests {
         #[test]
         pub fn $name() {
             let (hint, line, expected) = $input;
-            assert_eq!(
-                str_from_placements(Game::place_all_left(hint, &line_from_str(line))),
-                expected.map(|s| s.to_owned())
-            )
+            test_line(hint, line, expected)
         }
     )*
     }
@@ -53,7 +57,7 @@ macro_rules! right_line_tests {
         pub fn $name() {
             let (hint, line, expected) = $input;
             assert_eq!(
-                str_from_placements(Game::place_all_right(hint, &line_from_str(line))),
+                str_from_placements(Game::place_all_right(&black(hint), &line_from_str(line))),
                 expected.map(|s| s.to_owned())
             )
         }
@@ -66,7 +70,11 @@ fn str_from_line(line: Vec<Square>) -> String {
         .map(|s| match s {
             Square::Unknown => '_',
             Square::Empty => 'x',
-            Square::Filled => 'o',
+            Square::Filled(0) => 'o',
+            Square::Filled(1) => 'r',
+            Square::Filled(2) => 'g',
+            Square::Filled(_) => panic!("ruh roh"),
+            Square::Triangle(corner) => corner.glyph(),
         })
         .collect()
 }
@@ -78,7 +86,7 @@ macro_rules! overlap_tests {
         pub fn $name() {
             let (hint, line, expected) = $input;
             assert_eq!(
-                str_from_line(Game::refine_line(&line_from_str(line), hint).0),
+                str_from_line(Game::refine_line(&line_from_str(line), &black(hint)).unwrap().0),
                 expected,
             )
         }
@@ -86,6 +94,27 @@ macro_rules! overlap_tests {
     }
 }
 
+/// Statuses are written as one char per segment. _ for open, d for done, ! for violated
+macro_rules! status_tests {
+    ($($name:ident: $input:expr,)*) => {
+    $(
+        #[test]
+        pub fn $name() {
+            let (hint, line, expected) = $input;
+            let statuses: String = Game::segment_statuses(&black(hint), &line_from_str(line))
+                .iter()
+                .map(|status| match status {
+                    SegmentStatus::Open => '_',
+                    SegmentStatus::Done => 'd',
+                    SegmentStatus::Violated => '!',
+                })
+                .collect();
+            assert_eq!(statuses, expected)
+        }
+    )*
+    }
+}
+
 left_line_tests! {
     left_one_seg_1: (&[3], "_____", Some("000__")),
     left_one_seg_2: (&[3], "___o_", Some("_000_")),
@@ -119,3 +148,390 @@ overlap_tests! {
     // capping: (&[1, 1, 1, 1], "x_x__o_o__", "x_x_xoxox_"),
     i_dunno: (&[1, 1], "oxoxxxxx__", "oxoxxxxxxx"),
 }
+
+status_tests! {
+    status_untouched: (&[2, 1], "_____", "__"),
+    status_filled_but_could_move: (&[3], "_oo__", "_"),
+    status_run_fixed_by_filled: (&[2], "_oo__", "d"),
+    status_pinned: (&[2, 1], "oox__", "d_"),
+    status_pinned_by_other_seg: (&[2, 1], "oo_o_", "dd"),
+    status_run_too_long: (&[2, 1], "ooo__", "!!"),
+    status_too_many_runs: (&[1], "o_o__", "!"),
+}
+
+macro_rules! complete_tests {
+    ($($name:ident: $input:expr,)*) => {
+    $(
+        #[test]
+        pub fn $name() {
+            let (hint, line, expected) = $input;
+            assert_eq!(
+                Game::refine_line_complete(&line_from_str(line), &black(hint))
+                    .map(|(line, _, _)| str_from_line(line)),
+                expected.map(|s| s.to_owned()),
+            )
+        }
+    )*
+    }
+}
+
+complete_tests! {
+    complete_overlap_1: (&[4], "______", Some("__oo__")),
+    complete_overlap_2: (&[4], "oooo__", Some("ooooxx")),
+    complete_empty: (&[], "_____", Some("xxxxx")),
+    complete_i_dunno: (&[1, 1], "oxoxxxxx__", Some("oxoxxxxxxx")),
+    // overlap can't do this one
+    complete_capping: (&[1, 1, 1, 1], "x_x__o_o__", Some("x_x_xoxox_")),
+    complete_reach: (&[2, 1], "__o______o", Some("x_o_xxxxxo")),
+    complete_too_long: (&[3], "__", None::<&str>),
+    complete_bad_fill: (&[1], "o_o", None::<&str>),
+}
+
+/// Hint with colors, as (len, symbol) using the same symbols as line_from_str
+fn colored(segments: &[(u32, char)]) -> Hint {
+    segments
+        .iter()
+        .map(|(len, symbol)| {
+            let color = match symbol {
+                'o' => 0,
+                'r' => 1,
+                'g' => 2,
+                _ => panic!("ruh roh"),
+            };
+            Segment::new(*len, color)
+        })
+        .collect()
+}
+
+macro_rules! color_tests {
+    ($($name:ident: $input:expr,)*) => {
+    $(
+        #[test]
+        pub fn $name() {
+            let (hint, line, overlap, complete) = $input;
+            let hint = colored(hint);
+            let line = line_from_str(line);
+            assert_eq!(
+                Game::refine_line(&line, &hint).map(|(line, _, _)| str_from_line(line)),
+                overlap.map(|s| s.to_owned()),
+            );
+            assert_eq!(
+                Game::refine_line_complete(&line, &hint).map(|(line, _, _)| str_from_line(line)),
+                complete.map(|s| s.to_owned()),
+            );
+        }
+    )*
+    }
+}
+
+color_tests! {
+    // Different colors don't need a gap so this fills the line exactly
+    color_no_gap: (&[(2, 'r'), (2, 'g')], "____", Some("rrgg"), Some("rrgg")),
+    color_same_gap: (&[(2, 'r'), (1, 'r')], "____", Some("rrxr"), Some("rrxr")),
+    color_overlap: (&[(3, 'r')], "_____", Some("__r__"), Some("__r__")),
+    color_wrong_color: (&[(2, 'r')], "_o__", None::<&str>, None::<&str>),
+    color_pinned_by_other: (&[(1, 'r'), (2, 'g')], "___g", Some("__gg"), Some("__gg")),
+}
+
+#[test]
+pub fn color_hint_from_line() {
+    assert_eq!(
+        Game::line_to_hint(&line_from_str("rrgx_oo")),
+        colored(&[(2, 'r'), (1, 'g'), (2, 'o')])
+    );
+    assert!(Game::check_line(
+        &colored(&[(1, 'r'), (1, 'r')]),
+        &line_from_str("rxr")
+    ));
+    assert!(!Game::check_line(
+        &colored(&[(1, 'r'), (1, 'r')]),
+        &line_from_str("rr_")
+    ));
+}
+
+#[test]
+pub fn color_left_placement() {
+    assert_eq!(
+        str_from_placements(Game::place_all_left(
+            &colored(&[(2, 'r'), (2, 'g')]),
+            &line_from_str("_____")
+        )),
+        Some("0011_".to_owned())
+    );
+}
+
+/// Triangle hint, as (start, len, end) with the triangles as glyphs and ' ' for a whole square
+fn shaped(segments: &[(char, u32, char)]) -> Hint {
+    segments
+        .iter()
+        .map(|(start, len, end)| Segment {
+            start: Corner::from_glyph(*start),
+            end: Corner::from_glyph(*end),
+            ..Segment::from(*len)
+        })
+        .collect()
+}
+
+macro_rules! triangle_tests {
+    ($($name:ident: $input:expr,)*) => {
+    $(
+        #[test]
+        pub fn $name() {
+            let (hint, line, overlap, complete) = $input;
+            let hint = shaped(hint);
+            let line = line_from_str(line);
+            assert_eq!(
+                Game::refine_line(&line, &hint).map(|(line, _, _)| str_from_line(line)),
+                overlap.map(|s| s.to_owned()),
+            );
+            assert_eq!(
+                Game::refine_line_complete(&line, &hint).map(|(line, _, _)| str_from_line(line)),
+                complete.map(|s| s.to_owned()),
+            );
+        }
+    )*
+    }
+}
+
+triangle_tests! {
+    triangle_pair: (&[('◢', 2, '◣')], "__", Some("◢◣"), Some("◢◣")),
+    // Back to back triangles only touch at a point so they don't need a gap
+    triangle_no_gap: (&[('◣', 1, '◣'), ('◢', 1, '◢')], "__", Some("◣◢"), Some("◣◢")),
+    triangle_needs_gap: (&[('◢', 1, '◢'), (' ', 1, ' ')], "__", None::<&str>, None::<&str>),
+    // The ends could be either of 2 squares, but the middle is whole either way
+    triangle_overlap: (&[('◢', 4, '◣')], "_____", Some("__o__"), Some("__o__")),
+    triangle_pins_start: (&[('◢', 3, ' ')], "__◢__", Some("xx◢oo"), Some("xx◢oo")),
+    triangle_wrong_way: (&[('◢', 2, ' ')], "_◣__", None::<&str>, None::<&str>),
+}
+
+#[test]
+pub fn triangle_hint_from_line() {
+    assert_eq!(
+        Game::line_to_hint(&line_from_str("◢o◣x◣◢o")),
+        shaped(&[('◢', 3, '◣'), ('◣', 1, '◣'), ('◢', 2, ' ')])
+    );
+    assert!(!Game::check_line(
+        &shaped(&[('◢', 2, ' ')]),
+        &line_from_str("oo")
+    ));
+}
+
+#[test]
+pub fn triangle_right_placement() {
+    assert_eq!(
+        str_from_placements(Game::place_all_right(
+            &shaped(&[('◢', 2, ' '), (' ', 1, ' ')]),
+            &line_from_str("_____")
+        )),
+        Some("_00_1".to_owned())
+    );
+}
+
+macro_rules! cyclic_tests {
+    ($($name:ident: $input:expr,)*) => {
+    $(
+        #[test]
+        pub fn $name() {
+            let (hint, line, overlap, complete) = $input;
+            let hint = black(hint);
+            let line = line_from_str(line);
+            assert_eq!(
+                Game::refine_line_cyclic(&line, &hint, Game::refine_line)
+                    .map(|(line, _, _)| str_from_line(line)),
+                overlap.map(|s| s.to_owned()),
+            );
+            assert_eq!(
+                Game::refine_line_cyclic(&line, &hint, Game::refine_line_complete)
+                    .map(|(line, _, _)| str_from_line(line)),
+                complete.map(|s| s.to_owned()),
+            );
+        }
+    )*
+    }
+}
+
+cyclic_tests! {
+    // Both ends filled can only be the one segment going round
+    cyclic_across: (&[2], "o___o", Some("oxxxo"), Some("oxxxo")),
+    // Straight this would have to cover the middle, but round it can go anywhere
+    cyclic_no_overlap: (&[4], "_____", Some("_____"), Some("_____")),
+    // The last square is right next to the first going round, so it needs a gap too
+    cyclic_gap: (&[1, 1], "o___", Some("oxox"), Some("oxox")),
+    cyclic_full: (&[3], "___", Some("ooo"), Some("ooo")),
+    cyclic_too_long: (&[2, 2], "_____", None::<&str>, None::<&str>),
+}
+
+#[test]
+pub fn cyclic_mystery() {
+    let refine = |line| {
+        Game::refine_line_cyclic(&line_from_str(line), &mystery(&[None]), Game::refine_line)
+            .map(|(line, _, _)| str_from_line(line))
+    };
+    assert_eq!(refine("oxxxo"), Some("oxxxo".to_owned()));
+    assert_eq!(refine("o_x_o"), Some("o_x_o".to_owned()));
+    assert_eq!(refine("oxoxo"), None);
+}
+
+/// Every way of filling in the unknowns in a wrapped line that fits hint, agreed on. What
+/// refine_line_cyclic should come up with
+fn brute_force_cyclic(line: &[Square], hint: &[Segment]) -> Option<Vec<Square>> {
+    let mut options = vec![Square::Empty, Square::Filled(0)];
+    options.extend(
+        hint.iter()
+            .flat_map(|seg| [seg.start, seg.end])
+            .flatten()
+            .map(Square::Triangle),
+    );
+    let mut ways = vec![vec![]];
+    for square in line {
+        let next = match square {
+            Square::Unknown => options.clone(),
+            square => vec![square.clone()],
+        };
+        ways = ways
+            .iter()
+            .flat_map(|way: &Vec<Square>| {
+                next.iter().map(move |square| {
+                    let mut way = way.clone();
+                    way.push(square.clone());
+                    way
+                })
+            })
+            .collect();
+    }
+    ways.into_iter()
+        .filter(|way| Game::hint_allows(hint, &Game::cyclic_line_to_hint(way)))
+        .fold(None, |agreed, way| Some(Game::agree(agreed, way)))
+}
+
+#[test]
+pub fn cyclic_triangles_match_brute_force() {
+    let hints = ["◥2", "3◣", "◢3◤", "◥1", "1 ◥2", "◢2 1", "2◣ ◥1"];
+    let palette = [PaletteColor::black()];
+    for hint in hints {
+        let hint = parse_hint(1, hint, &palette, false).expect("test hints are fine");
+        for len in 2..=5 {
+            let squares = ["_", "o", "x", "◥", "◣"];
+            for n in 0..squares.len().pow(len) {
+                let line: String = (0..len)
+                    .map(|i| squares[n / squares.len().pow(i) % squares.len()])
+                    .collect();
+                let line = line_from_str(&line);
+                assert_eq!(
+                    Game::refine_line_cyclic(&line, &hint, Game::refine_line_complete)
+                        .map(|(line, _, _)| line),
+                    brute_force_cyclic(&line, &hint),
+                    "{:?} {}",
+                    hint,
+                    str_from_line(line.clone())
+                );
+            }
+        }
+    }
+}
+
+#[test]
+pub fn cyclic_hint_from_line() {
+    let hint = |line| Game::cyclic_line_to_hint(&line_from_str(line));
+    // The segment going round the end comes last
+    assert_eq!(hint("oxoxo"), black(&[1, 2]));
+    assert_eq!(hint("oxxoo"), black(&[3]));
+    assert_eq!(hint("ooooo"), black(&[5]));
+    assert_eq!(hint("xxxxx"), black(&[]));
+    assert_eq!(hint("rxgo"), colored(&[(1, 'r'), (1, 'g'), (1, 'o')]));
+    assert_eq!(hint("rxgr"), colored(&[(1, 'g'), (2, 'r')]));
+}
+
+/// Black hint with None for segments of hidden length
+fn mystery(segments: &[Option<u32>]) -> Hint {
+    segments
+        .iter()
+        .map(|len| match len {
+            Some(len) => Segment::from(*len),
+            None => Segment::hidden(0),
+        })
+        .collect()
+}
+
+macro_rules! mystery_tests {
+    ($($name:ident: $input:expr,)*) => {
+    $(
+        #[test]
+        pub fn $name() {
+            let (hint, line, expected) = $input;
+            let hint = mystery(hint);
+            let line = line_from_str(line);
+            // Overlap hands these off to the complete solver, so they'd better agree
+            assert_eq!(
+                Game::refine_line(&line, &hint).map(|(line, _, _)| str_from_line(line)),
+                expected.map(|s| s.to_owned()),
+            );
+            assert_eq!(
+                Game::refine_line_complete(&line, &hint).map(|(line, _, _)| str_from_line(line)),
+                expected.map(|s| s.to_owned()),
+            );
+        }
+    )*
+    }
+}
+
+mystery_tests! {
+    mystery_fills_between: (&[None], "_o_o_", Some("_ooo_")),
+    mystery_no_room: (&[None, Some(3)], "_____", Some("oxooo")),
+    mystery_anywhere: (&[None], "_____", Some("_____")),
+    mystery_too_many: (&[None, None, None], "____", None::<&str>),
+    mystery_two_runs: (&[None], "o_o", Some("ooo")),
+}
+
+#[test]
+pub fn mystery_hint_checks() {
+    assert!(Game::check_line(
+        &mystery(&[None, Some(1)]),
+        &line_from_str("ooxo")
+    ));
+    assert!(!Game::check_line(
+        &mystery(&[None, Some(1)]),
+        &line_from_str("oxoo")
+    ));
+    assert!(!Game::check_line(&mystery(&[None]), &line_from_str("xxx")));
+    assert!(Game::check_line(
+        &Segment::missing_clue(),
+        &line_from_str("oxo")
+    ));
+}
+
+/// Mega clue tests take the groups and the 2 lines, and expect the 2 lines back
+macro_rules! pair_tests {
+    ($($name:ident: $input:expr,)*) => {
+    $(
+        #[test]
+        pub fn $name() {
+            let (groups, (first, second), expected): (&[u32], _, Option<(&str, &str)>) = $input;
+            assert_eq!(
+                Game::refine_pair(&line_from_str(first), &line_from_str(second), groups)
+                    .map(|(first, second, _, _)| (str_from_line(first), str_from_line(second))),
+                expected.map(|(first, second)| (first.to_owned(), second.to_owned())),
+            );
+        }
+    )*
+    }
+}
+
+pair_tests! {
+    pair_fills_everything: (&[6], ("___", "___"), Some(("ooo", "ooo"))),
+    pair_splits_on_corners: (&[1, 1], ("o_", "_o"), Some(("ox", "xo"))),
+    pair_gap_between: (&[2, 2], ("___", "___"), Some(("oxo", "oxo"))),
+    pair_one_line_empty: (&[3], ("____", "xxxx"), Some(("_oo_", "xxxx"))),
+    pair_too_big: (&[2], ("ooo", "___"), None),
+    pair_no_room: (&[3, 3], ("___", "___"), None),
+}
+
+#[test]
+pub fn pair_groups_from_lines() {
+    let groups =
+        |first, second| Game::pair_to_groups(&line_from_str(first), &line_from_str(second));
+    assert_eq!(groups("oox", "xoo"), vec![4]);
+    assert_eq!(groups("oxo", "xox"), vec![1, 1, 1]);
+    assert_eq!(groups("o_o", "ooo"), vec![5]);
+    assert_eq!(groups("xxx", "xxx"), vec![]);
+}
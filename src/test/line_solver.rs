@@ -0,0 +1,146 @@
+use crate::{
+    game::{Corner, Game, Hint, Outcome, Segment, Solver, Square},
+    line_solver::{BruteForce, Cached, Complete, LineSolver, Overlap},
+    test::black,
+};
+
+/// Every line of len made of unknown, filled and empty squares
+fn every_line(len: usize) -> Vec<Vec<Square>> {
+    let squares = [Square::Unknown, Square::Filled(0), Square::Empty];
+    (0..squares.len().pow(len as u32))
+        .map(|n| {
+            (0..len)
+                .map(|i| squares[n / squares.len().pow(i as u32) % squares.len()].clone())
+                .collect()
+        })
+        .collect()
+}
+
+#[test]
+pub fn complete_matches_brute_force() {
+    let hints = [
+        black(&[]),
+        black(&[1]),
+        black(&[2]),
+        black(&[1, 1]),
+        black(&[3, 1]),
+        black(&[1, 2, 1]),
+        black(&[6]),
+        vec![Segment::hidden(0), Segment::from(1)],
+    ];
+    for hint in &hints {
+        for line in every_line(6) {
+            assert_eq!(
+                Complete.refine_line(&line, hint),
+                BruteForce.refine_line(&line, hint),
+                "{:?} {:?}",
+                hint,
+                line
+            );
+        }
+    }
+}
+
+#[test]
+pub fn complete_matches_brute_force_with_colors_and_triangles() {
+    let triangle = Segment {
+        end: Some(Corner::BottomLeft),
+        ..Segment::new(2, 0)
+    };
+    let hint = vec![Segment::new(1, 0), Segment::new(2, 1), triangle];
+    let mut line = vec![Square::Unknown; 7];
+    assert_eq!(
+        Complete.refine_line(&line, &hint),
+        BruteForce.refine_line(&line, &hint)
+    );
+    line[1] = Square::Filled(1);
+    assert_eq!(
+        Complete.refine_line(&line, &hint),
+        BruteForce.refine_line(&line, &hint)
+    );
+}
+
+#[test]
+pub fn overlap_never_finds_more_than_complete() {
+    let hint = black(&[2, 1]);
+    for line in every_line(5) {
+        let complete = Complete.refine_line(&line, &hint);
+        let Some((overlap, _, _)) = Overlap.refine_line(&line, &hint) else {
+            assert_eq!(complete, None, "{:?}", line);
+            continue;
+        };
+        let (complete, _, _) = complete.expect("overlap found a way");
+        for (overlap, complete) in overlap.iter().zip(&complete) {
+            assert!(
+                *overlap == Square::Unknown || overlap == complete,
+                "{:?}",
+                line
+            );
+        }
+    }
+}
+
+#[test]
+pub fn solvers_agree_on_a_whole_puzzle() {
+    let game = crate::sample_game();
+    let mut complete = Solver::with_line_solver(game.clone(), Complete);
+    let mut brute_force = Solver::with_line_solver(game, BruteForce);
+    assert_eq!(complete.solve(&mut None), Outcome::Solved);
+    assert_eq!(brute_force.solve(&mut None), Outcome::Solved);
+    assert_eq!(complete.game.grid, brute_force.game.grid);
+    assert_eq!(complete.stats, brute_force.stats);
+}
+
+#[test]
+pub fn cache_remembers_lines() {
+    let mut cached = Cached::new(Complete);
+    let hint = black(&[3]);
+    let line = vec![Square::Unknown; 4];
+    let first = cached.refine_line(&line, &hint);
+    assert_eq!(cached.refine_line(&line, &hint), first);
+    assert_eq!(first, Complete.refine_line(&line, &hint));
+    // Same line under a different hint is something else
+    cached.refine_line(&line, &black(&[1]));
+    let stats = cached.stats();
+    assert_eq!((stats.hits, stats.misses, stats.len), (1, 2, 2));
+}
+
+#[test]
+pub fn cache_stays_under_capacity() {
+    let mut cached = Cached::with_capacity(Complete, 3);
+    let hint = black(&[1]);
+    for line in every_line(3) {
+        assert_eq!(
+            cached.refine_line(&line, &hint),
+            Complete.refine_line(&line, &hint)
+        );
+        assert!(cached.stats().len <= 3);
+    }
+    assert_eq!(cached.stats().misses, 27);
+}
+
+#[test]
+pub fn cache_is_shared_with_branches() {
+    let game = crate::sample_game();
+    let cached = Cached::new(Complete);
+    let mut with_cache = Solver::with_line_solver(game.clone(), cached.clone());
+    let mut without = Solver::with_line_solver(game, Complete);
+    assert_eq!(with_cache.search(2), without.search(2));
+    assert_eq!(with_cache.stats, without.stats);
+    let misses = cached.stats().misses;
+    // Going over it again from scratch only ever hits
+    Solver::with_line_solver(crate::sample_game(), cached.clone()).search(2);
+    assert_eq!(cached.stats().misses, misses);
+    assert!(cached.stats().hits > 0);
+}
+
+#[test]
+pub fn brute_force_budget_counts_colors() {
+    assert!(BruteForce::fits(&crate::sample_game()));
+    // 5 options over 15 squares is far too many fillings, even if 15 black squares isn't
+    let mut game = Game::new(vec![vec![1]; 15], vec![vec![1]; 15]).unwrap();
+    assert!(BruteForce::fits(&game));
+    let colors: Hint = (0..4).map(|color| Segment::new(1, color)).collect();
+    game.row_hints = vec![colors; 15];
+    assert!(!BruteForce::fits(&game));
+}
@@ -1 +1,27 @@
+pub mod cube;
+pub mod deduction;
+pub mod difficulty;
+pub mod edit;
+pub mod generate;
+pub mod hex;
+pub mod import;
 pub mod line;
+pub mod line_solver;
+pub mod play;
+pub mod raster;
+pub mod render;
+pub mod solution;
+pub mod svg;
+pub mod text;
+pub mod topology;
+
+use crate::game::{Hint, Segment};
+
+/// Black segments, so black and white tests can keep writing hints as plain numbers
+pub fn black(lens: &[u32]) -> Hint {
+    lens.iter().map(|len| Segment::from(*len)).collect()
+}
+
+pub fn black_hints(hints: Vec<Vec<u32>>) -> Vec<Hint> {
+    hints.iter().map(|hint| black(hint)).collect()
+}
@@ -0,0 +1,122 @@
+use crossterm::event::{KeyCode, KeyEvent};
+
+use crate::{
+    game::{Game, Square},
+    play::Play,
+};
+
+fn press(play: &mut Play, keys: &str) {
+    for c in keys.chars() {
+        play.handle_key(KeyEvent::from(KeyCode::Char(c)));
+    }
+}
+
+fn grid_str(play: &Play) -> String {
+    play.game
+        .grid
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|s| match s {
+                    Square::Unknown => '_',
+                    Square::Filled(_) => 'o',
+                    Square::Triangle(corner) => corner.glyph(),
+                    Square::Empty => 'x',
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn new_play() -> Play {
+    // oo_
+    // __o
+    Play::new(Game::from_hints_str("2\n1\n\n1\n1\n1\n").unwrap())
+}
+
+#[test]
+pub fn auto_cross_finishes_lines() {
+    let mut play = new_play();
+    press(&mut play, "a ");
+    assert_eq!(grid_str(&play), "o__/x__");
+    // Row 0 and col 1 both get finished by the same square
+    press(&mut play, "l ");
+    assert_eq!(grid_str(&play), "oox/xx_");
+}
+
+#[test]
+pub fn auto_cross_catches_up_when_turned_on() {
+    let mut play = new_play();
+    press(&mut play, " l ");
+    assert_eq!(grid_str(&play), "oo_/___");
+    press(&mut play, "a");
+    assert_eq!(grid_str(&play), "oox/xx_");
+}
+
+#[test]
+pub fn undo_takes_move_and_auto_cross_together() {
+    let mut play = new_play();
+    press(&mut play, "a ");
+    press(&mut play, "u");
+    assert_eq!(grid_str(&play), "___/___");
+    press(&mut play, "U");
+    assert_eq!(grid_str(&play), "o__/x__");
+    press(&mut play, "l u");
+    assert_eq!(grid_str(&play), "o__/x__");
+}
+
+#[test]
+pub fn stroke_paints_and_undoes_at_once() {
+    let mut play = new_play();
+    press(&mut play, " LL");
+    assert_eq!(grid_str(&play), "ooo/___");
+    press(&mut play, "u");
+    assert_eq!(grid_str(&play), "___/___");
+}
+
+#[test]
+pub fn stroke_stays_on_its_line() {
+    let mut play = new_play();
+    // Starts going right so the J doesn't leave row 0
+    press(&mut play, "xLJ");
+    assert_eq!(grid_str(&play), "xx_/___");
+    assert_eq!(play.cursor, (0, 1));
+    // A new stroke that starts on a crossed square clears instead
+    press(&mut play, "hxL");
+    assert_eq!(grid_str(&play), "___/___");
+}
+
+#[test]
+pub fn digits_pick_the_color() {
+    let mut play = Play::new(Game::from_hints_str("colors r:ff0000\n1 1r\n\n1\n0\n1r\n").unwrap());
+    press(&mut play, "2ll 1hh ");
+    assert_eq!(play.game.grid[0][2], Square::Filled(1));
+    assert_eq!(play.game.grid[0][0], Square::Filled(0));
+    assert!(play.is_solved());
+    // Only as many colors as the palette has
+    press(&mut play, "5");
+    assert_eq!(play.color, 0);
+}
+
+#[test]
+pub fn wrapped_lines_join_up_round_the_end() {
+    let mut play = Play::new(Game::from_hints_str("wrap\n2\n\n1\n0\n1\n").unwrap());
+    press(&mut play, " ll");
+    assert!(!play.is_solved());
+    press(&mut play, " ");
+    assert_eq!(grid_str(&play), "o_o");
+    assert!(play.is_solved());
+}
+
+#[test]
+pub fn mega_lines_only_finish_together() {
+    let mut play = Play::new(Game::from_hints_str("3\n^\n\n1\n2\n").unwrap());
+    // The first line being filled in doesn't cross anything out, it has no clue of its own
+    press(&mut play, " l ");
+    assert_eq!(grid_str(&play), "oo/__");
+    assert!(!play.is_solved());
+    press(&mut play, "j ");
+    assert_eq!(grid_str(&play), "oo/_o");
+    assert!(play.is_solved());
+}
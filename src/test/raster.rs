@@ -0,0 +1,39 @@
+use crate::game::{Game, Square};
+
+/// #.
+/// ?#
+fn small_game() -> Game {
+    let mut game = Game::new(vec![vec![1], vec![1]], vec![vec![1], vec![1]]).unwrap();
+    game.grid = vec![
+        vec![Square::Filled(0), Square::Empty],
+        vec![Square::Unknown, Square::Filled(0)],
+    ];
+    game
+}
+
+#[test]
+pub fn pbm_matches_reference() {
+    assert_eq!(small_game().to_pbm(1), b"P4\n2 2\n\x80\x40".to_vec());
+    // Scaled up each square is a 3x3 block
+    assert_eq!(
+        small_game().to_pbm(3),
+        b"P4\n6 6\n\xe0\xe0\xe0\x1c\x1c\x1c".to_vec()
+    );
+}
+
+#[test]
+pub fn pgm_keeps_unknowns_grey() {
+    let pgm = small_game().to_pgm(1);
+    assert_eq!(pgm, b"P5\n2 2\n255\n\x00\xff\xa0\x00".to_vec());
+}
+
+#[test]
+pub fn png_round_trips() {
+    let png_bytes = small_game().to_png(2).unwrap();
+    let decoder = png::Decoder::new(png_bytes.as_slice());
+    let mut reader = decoder.read_info().unwrap();
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).unwrap();
+    assert_eq!((info.width, info.height), (4, 4));
+    assert_eq!(buf, small_game().to_pixels(2).2);
+}
@@ -0,0 +1,49 @@
+use crate::{game::Game, render::Highlight};
+
+fn game(rows: usize, cols: usize) -> Game {
+    Game::new(vec![vec![1]; cols], vec![vec![1]; rows]).unwrap()
+}
+
+#[test]
+pub fn separators_add_lines_between_blocks() {
+    let mut game = game(12, 11);
+    let plain_size = {
+        game.separator_every = None;
+        let rendered = game.render_all(&Highlight::default());
+        (rendered.len(), rendered[0].len())
+    };
+    game.separator_every = Some(5);
+    let rendered = game.render_all(&Highlight::default());
+    // 12 rows get 2 separators, 11 cols get 2 too. Nothing after the last block
+    assert_eq!(rendered.len(), plain_size.0 + 2);
+    assert_eq!(rendered[0].len(), plain_size.1 + 2);
+}
+
+#[test]
+pub fn screen_mapping_round_trips() {
+    let mut game = game(12, 11);
+    game.separator_every = Some(5);
+    game.render_all(&Highlight::default());
+    for row in 0..12 {
+        for col in 0..11 {
+            let (x, y) = game.screen_pos(row, col);
+            assert_eq!(game.cell_at(x, y), Some((row, col)));
+        }
+    }
+    // Just right of col 4 is the separator
+    let (x, y) = game.screen_pos(0, 4);
+    assert_eq!(game.cell_at(x + 1, y), None);
+    assert_eq!(game.cell_at(x + 2, y), Some((0, 5)));
+}
+
+#[test]
+pub fn ambiguous_cells_stand_out() {
+    let game = game(2, 2);
+    let plain = game.render_grid(&Highlight::default());
+    let marked = game.render_grid(&Highlight {
+        ambiguous: vec![(0, 1)],
+        ..Default::default()
+    });
+    assert_eq!(plain[0][0], marked[0][0]);
+    assert_ne!(plain[0][1], marked[0][1]);
+}
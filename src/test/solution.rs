@@ -0,0 +1,208 @@
+use crate::{
+    game::{Game, Square},
+    generate::ambiguous_cells,
+};
+
+const PUZZLE: &str = "2\n1\n\n1\n2\n";
+const PUZZLE_WITH_SOLUTION: &str = "2\n1\n\n1\n2\n\noo\nxo\n";
+
+#[test]
+pub fn parses_solution_block() {
+    let game = Game::from_hints_str(PUZZLE_WITH_SOLUTION).unwrap();
+    assert_eq!(
+        game.solution,
+        Some(vec![
+            vec![Square::Filled(0), Square::Filled(0)],
+            vec![Square::Empty, Square::Filled(0)]
+        ])
+    );
+    assert_eq!(game.hints_to_string(), PUZZLE_WITH_SOLUTION);
+}
+
+#[test]
+pub fn rejects_wrong_sized_solution() {
+    assert!(Game::from_hints_str("2\n1\n\n1\n2\n\nooo\nxo\n").is_err());
+}
+
+#[test]
+pub fn rejects_solution_that_breaks_the_clues() {
+    // Right size, but the rows are the wrong way round
+    assert!(Game::from_hints_str("2\n1\n\n1\n2\n\nxo\noo\n").is_err());
+    // Fits the rows but not the cols
+    assert!(Game::from_hints_str("1\n1\n\n1\n1\n\nox\nox\n").is_err());
+}
+
+#[test]
+pub fn rejects_hints_too_long_for_their_line() {
+    assert!(Game::from_hints_str("5\n\n1\n").is_err());
+    assert!(Game::from_hints_str("1 1\n\n1\n1\n").is_err());
+    assert!(Game::from_hints_str("1\n\n1\n1 1\n").is_err());
+    // Different colors can sit right up against each other
+    assert!(Game::from_hints_str("colors r:ff0000\n1 1r\n\n1\n1r\n").is_ok());
+    // Mega groups spread over both lines
+    assert!(Game::from_hints_str("3\n^\n\n2\n2\n").is_ok());
+}
+
+#[test]
+pub fn finds_solution_when_missing() {
+    let mut game = Game::from_hints_str(PUZZLE).unwrap();
+    assert!(game.find_solution());
+    assert_eq!(
+        game.solution,
+        Game::from_hints_str(PUZZLE_WITH_SOLUTION).unwrap().solution
+    );
+}
+
+#[test]
+pub fn mistakes_ignore_unknowns() {
+    let mut game = Game::from_hints_str(PUZZLE_WITH_SOLUTION).unwrap();
+    game.grid[0][0] = Square::Filled(0);
+    game.grid[0][1] = Square::Empty;
+    game.grid[1][0] = Square::Filled(0);
+    assert_eq!(game.mistakes(), vec![(0, 1), (1, 0)]);
+}
+
+#[test]
+pub fn ambiguity_is_where_solutions_differ() {
+    let diagonals = Game::from_hints_str("1\n1\n\n1\n1\n").unwrap();
+    assert_eq!(ambiguous_cells(&diagonals, true).len(), 4);
+    // Line logic gets this one on its own
+    assert!(ambiguous_cells(&Game::from_hints_str(PUZZLE).unwrap(), true).is_empty());
+    // Same diagonals, but the empty row under them is pinned down
+    let partly = Game::from_hints_str("1\n1\n0\n\n1\n1\n").unwrap();
+    let ambiguous = ambiguous_cells(&partly, true);
+    assert_eq!(ambiguous.len(), 4);
+    assert!(!ambiguous.contains(&(2, 0)));
+}
+
+#[test]
+pub fn ambiguity_of_unique_puzzle_is_what_logic_misses() {
+    let game = crate::generate::generate(&crate::generate::GeneratorOptions {
+        rows: 8,
+        cols: 8,
+        tier: Some(crate::difficulty::Tier::Probing),
+        ..Default::default()
+    })
+    .unwrap();
+    assert_eq!(game.line_solve(), None);
+    // The one solution doesn't disagree with anything
+    assert!(ambiguous_cells(&game, true).is_empty());
+    let ambiguous = ambiguous_cells(&game, false);
+    assert!(!ambiguous.is_empty());
+    let mut solver = crate::game::Solver::new(game.clone());
+    solver.solve(&mut None);
+    for (row, col) in ambiguous {
+        assert_eq!(solver.game.grid[row][col], Square::Unknown);
+    }
+}
+
+// rro
+// oxr
+const COLOR_PUZZLE: &str = "colors r:ff0000\n2r 1\n1 1r\n\n1r 1\n1r\n1 1r\n\nrro\noxr\n";
+
+#[test]
+pub fn color_puzzle_round_trips_and_solves() {
+    let game = Game::from_hints_str(COLOR_PUZZLE).unwrap();
+    assert_eq!(game.palette.len(), 2);
+    assert_eq!(game.palette[1].rgb, (255, 0, 0));
+    assert_eq!(game.hints_to_string(), COLOR_PUZZLE);
+    let solution = vec![
+        vec![Square::Filled(1), Square::Filled(1), Square::Filled(0)],
+        vec![Square::Filled(0), Square::Empty, Square::Filled(1)],
+    ];
+    assert_eq!(game.solution, Some(solution.clone()));
+    assert_eq!(game.line_solve(), Some(solution.clone()));
+    let mut blank = game.clone();
+    blank.grid = vec![vec![Square::Unknown; 3]; 2];
+    assert_eq!(crate::game::Solver::new(blank).search(2), vec![solution]);
+}
+
+#[test]
+pub fn rejects_unknown_colors() {
+    assert!(Game::from_hints_str("2g\n\n1\n1\n").is_err());
+    assert!(Game::from_hints_str("colors r:ff00\n1\n\n1\n").is_err());
+    assert!(Game::from_hints_str("colors x:ff0000\n1\n\n1\n").is_err());
+}
+
+// ◢o◣
+// ooo
+// ◥o◤
+const DIAMOND: &str = "◢3◣\n3\n◥3◤\n\n◢3◥\n3\n◣3◤\n\n◢o◣\nooo\n◥o◤\n";
+
+#[test]
+pub fn triangle_puzzle_round_trips_and_solves() {
+    let game = Game::from_hints_str(DIAMOND).unwrap();
+    assert_eq!(game.hints_to_string(), DIAMOND);
+    let solution = game.solution.clone().unwrap();
+    // Col hints read top to bottom, so the same triangles mean something else down a col
+    assert_eq!(
+        Game::from_solution(solution.clone()).hints_to_string(),
+        DIAMOND
+    );
+    assert_eq!(game.line_solve(), Some(solution.clone()));
+    let mut blank = game.clone();
+    blank.grid = vec![vec![Square::Unknown; 3]; 3];
+    assert_eq!(crate::game::Solver::new(blank).search(2), vec![solution]);
+}
+
+#[test]
+pub fn triangles_only_go_where_they_join_up() {
+    // ◣ doesn't cover its right side, so it can't start a run along a row
+    assert!(Game::from_hints_str("◣2\n\n1\n1\n").is_err());
+    // But going down a col it covers the bottom, so it can start one there
+    assert!(Game::from_hints_str("1\n1\n\n◣2\n").is_ok());
+    assert!(Game::from_hints_str("◢1◣\n\n1\n").is_err());
+}
+
+// Going round, the second row is a 3
+// xoxo
+// ooxo
+// xoxo
+// oxxx
+const WRAPPED: &str = "wrap\n1 1\n3\n1 1\n1\n\n1 1\n3\n0\n3\n\nxoxo\nooxo\nxoxo\noxxx\n";
+
+#[test]
+pub fn wrapped_puzzle_round_trips_and_solves() {
+    let game = Game::from_hints_str(WRAPPED).unwrap();
+    assert!(game.wrap);
+    assert_eq!(game.hints_to_string(), WRAPPED);
+    let solution = game.solution.clone().unwrap();
+    assert_eq!(
+        Game::wrapped_from_solution(solution.clone()).hints_to_string(),
+        WRAPPED
+    );
+    let mut blank = game.clone();
+    blank.grid = vec![vec![Square::Unknown; 4]; 4];
+    assert_eq!(crate::game::Solver::new(blank).search(2), vec![solution]);
+}
+
+// oxo
+// oox
+const MYSTERY: &str = "? 1\n*\n\n2\n?\n1\n\noxo\noox\n";
+
+#[test]
+pub fn mystery_puzzle_round_trips_and_solves() {
+    let game = Game::from_hints_str(MYSTERY).unwrap();
+    assert_eq!(game.hints_to_string(), MYSTERY);
+    assert_eq!(game.line_solve(), game.solution);
+    assert_eq!(game.to_string(), "    2 ? 1\n? 1 . . .\n  * . . .");
+}
+
+// The 3 and the 1 only touch corner to corner
+const MEGA: &str = "3 1\n^\n1\n\n1 1\n1\n1\n1\n\nooox\nxxxo\noxxx\n";
+
+#[test]
+pub fn mega_puzzle_round_trips_and_solves() {
+    let game = Game::from_hints_str(MEGA).unwrap();
+    assert_eq!(game.mega_rows[0].groups, vec![3, 1]);
+    assert_eq!(game.hints_to_string(), MEGA);
+    assert_eq!(game.line_solve(), game.solution);
+}
+
+#[test]
+pub fn mega_clues_need_a_plain_line_before() {
+    assert!(Game::from_hints_str("^\n1\n\n1\n").is_err());
+    assert!(Game::from_hints_str("1\n^\n^\n\n1\n").is_err());
+    assert!(Game::from_hints_str("*\n^\n\n1\n").is_err());
+    assert!(Game::from_hints_str("wrap\n1\n^\n\n1\n").is_err());
+}
@@ -0,0 +1,48 @@
+use crate::{
+    game::{Game, Square},
+    generate::ambiguous_cells,
+};
+
+fn count(svg: &str, tag: &str) -> usize {
+    svg.matches(tag).count()
+}
+
+#[test]
+pub fn blank_puzzle_has_clues_and_lines_only() {
+    let game = Game::from_hints_str("2\n1 1\n\n2\n1\n1\n").unwrap();
+    let svg = game.to_svg(false);
+    assert!(svg.starts_with("<svg"));
+    assert!(svg.trim_end().ends_with("</svg>"));
+    // One text per segment, one line per grid edge
+    assert_eq!(count(&svg, "<text"), 6);
+    assert_eq!(count(&svg, "<line"), 3 + 4);
+    // Just the background
+    assert_eq!(count(&svg, "<rect"), 1);
+}
+
+#[test]
+pub fn grid_draws_filled_and_empty() {
+    let mut game = Game::from_hints_str("2\n1 1\n\n2\n1\n1\n").unwrap();
+    game.grid[0][0] = Square::Filled(0);
+    game.grid[0][2] = Square::Empty;
+    let svg = game.to_svg(true);
+    assert_eq!(count(&svg, "<rect"), 2);
+    assert_eq!(count(&svg, "<path"), 1);
+}
+
+#[test]
+pub fn guides_are_thick() {
+    let game = Game::new(vec![vec![1]; 10], vec![vec![1]; 10]).unwrap();
+    let svg = game.to_svg(false);
+    // Both edges and the guide at 5, for rows and cols
+    assert_eq!(count(&svg, r#"stroke-width="2""#), 6);
+}
+
+#[test]
+pub fn marked_squares_are_shaded() {
+    let game = Game::from_hints_str("1\n1\n\n1\n1\n").unwrap();
+    let svg = game.to_svg_marked(false, &ambiguous_cells(&game, true));
+    // Background plus one per square
+    assert_eq!(count(&svg, "<rect"), 5);
+    assert_eq!(count(&svg, "magenta"), 4);
+}
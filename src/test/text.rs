@@ -0,0 +1,62 @@
+use crate::{
+    game::{Game, Glyphs},
+    generate::ambiguous_cells,
+};
+
+#[test]
+pub fn prints_solved_board() {
+    let mut game = Game::from_hints_str("2\n1 1\n\n2\n1\n1\n").unwrap();
+    assert!(game.find_solution());
+    game.grid = game.solution.clone().unwrap();
+    assert_eq!(game.to_string(), "    2 1 1\n  2 # # x\n1 1 # x #");
+}
+
+#[test]
+pub fn aligns_multi_digit_clues() {
+    let game = Game::from_hints_str(&format!("{}\n10\n0\n", "1\n".repeat(10))).unwrap();
+    let glyphs = Glyphs {
+        unknown: '?',
+        filled: 'o',
+        empty: '-',
+    };
+    let text = game.to_text(&glyphs);
+    let lines: Vec<_> = text.lines().collect();
+    assert_eq!(lines[0], "  10");
+    assert_eq!(lines[1], "1  ?  ?");
+    assert_eq!(lines.len(), 11);
+}
+
+#[test]
+pub fn marks_ambiguous_squares() {
+    let game = Game::from_hints_str("1\n1\n\n1\n1\n").unwrap();
+    let text = game.to_text_marked(&Glyphs::default(), &ambiguous_cells(&game, true));
+    assert_eq!(text, "  1 1\n1 ? ?\n1 ? ?");
+}
+
+#[test]
+pub fn colors_print_as_their_symbols() {
+    let mut game = Game::from_hints_str("colors r:ff0000\n2r 1\n\n1r\n1r\n1\n").unwrap();
+    assert!(game.find_solution());
+    game.grid = game.solution.clone().unwrap();
+    assert_eq!(game.to_string(), "     1r 1r  1\n2r 1  r  r  #");
+}
+
+#[test]
+pub fn triangles_print_as_their_glyphs() {
+    let mut game = Game::from_hints_str("◢2\n\n◢1\n1\n").unwrap();
+    assert!(game.find_solution());
+    game.grid = game.solution.clone().unwrap();
+    assert_eq!(game.to_string(), "   ◢1  1\n◢2  ◢  #");
+}
+
+#[test]
+pub fn wrapped_puzzles_get_marked() {
+    let game = Game::from_hints_str("wrap\n2\n\n1\n1\n").unwrap();
+    assert_eq!(game.to_string(), "↻ 1 1\n2 . .");
+}
+
+#[test]
+pub fn mega_clues_go_on_their_first_line() {
+    let game = Game::from_hints_str("3\n^\n\n1\n2\n").unwrap();
+    assert_eq!(game.to_string(), "  1 2\n3 . .\n^ . .");
+}
@@ -0,0 +1,45 @@
+use crate::{
+    game::{Corner, Game, Outcome, Square},
+    test::black,
+    topology::{Line, Topology},
+};
+
+#[test]
+pub fn propagates_over_any_lines() {
+    // 3 cells in a triangle, with a line along each side
+    let topology = Topology {
+        lines: vec![
+            Line::new(vec![0, 1], black(&[2])),
+            Line::new(vec![1, 2], black(&[1])),
+            Line::new(vec![2, 0], black(&[1])),
+        ],
+    };
+    let mut cells = vec![Square::Unknown; 3];
+    assert_eq!(
+        topology.propagate(&mut cells, |line, hint| Game::refine_line(line, hint)),
+        Outcome::Solved
+    );
+    assert_eq!(cells[2], Square::Empty);
+    let mut cells = vec![Square::Unknown, Square::Unknown, Square::Filled(0)];
+    assert_eq!(
+        topology.propagate(&mut cells, |line, hint| Game::refine_line(line, hint)),
+        Outcome::Contradiction
+    );
+}
+
+#[test]
+pub fn grid_cols_read_transposed() {
+    let mut game = Game::new(vec![vec![1], vec![]], vec![vec![1]]).unwrap();
+    game.grid[0][1] = Square::Triangle(Corner::TopRight);
+    let topology = game.topology();
+    assert_eq!(topology.lines.len(), 3);
+    let cells = game.grid.concat();
+    // The second col's one square, top right going down reads as bottom left going across
+    assert_eq!(
+        topology.read(&cells, 2),
+        vec![Square::Triangle(Corner::BottomLeft)]
+    );
+    let mut written = cells.clone();
+    topology.write(&mut written, 2, topology.read(&cells, 2));
+    assert_eq!(written, cells);
+}
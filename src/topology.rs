@@ -0,0 +1,105 @@
+use std::iter::zip;
+
+use crate::game::{Hint, Outcome, Refined, Square};
+
+/// A line through a puzzle: the cells it covers, in order, and its hint. Most puzzles use Hint,
+/// but the hint can be anything the refine function given to Topology knows how to read
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Line<H = Hint> {
+    /// Indices into the puzzle's cells
+    pub cells: Vec<usize>,
+    pub hint: H,
+    /// Squares come out transposed and go back in the same way. Used for cols of a grid, so line
+    /// logic only ever has to deal with triangles joining left and right
+    pub transposed: bool,
+}
+
+impl<H> Line<H> {
+    pub fn new(cells: Vec<usize>, hint: H) -> Self {
+        Line {
+            cells,
+            hint,
+            transposed: false,
+        }
+    }
+}
+
+/// The shape of a puzzle: a set of cells, which whoever owns the puzzle keeps, and the lines
+/// through them. Line logic goes over this line by line without caring whether it's a grid, a
+/// hex, a cube or something else entirely
+#[derive(Clone, Debug)]
+pub struct Topology<H = Hint> {
+    pub lines: Vec<Line<H>>,
+}
+
+impl<H> Topology<H> {
+    /// The squares on line i
+    pub fn read(&self, cells: &[Square], i: usize) -> Vec<Square> {
+        let line = &self.lines[i];
+        line.cells
+            .iter()
+            .map(|cell| match line.transposed {
+                true => cells[*cell].transposed(),
+                false => cells[*cell].clone(),
+            })
+            .collect()
+    }
+
+    /// Takes a line the way read gives it out
+    pub fn write(&self, cells: &mut [Square], i: usize, squares: Vec<Square>) {
+        let line = &self.lines[i];
+        for (cell, square) in zip(&line.cells, squares) {
+            cells[*cell] = match line.transposed {
+                true => square.transposed(),
+                false => square,
+            };
+        }
+    }
+
+    /// One pass of line logic over every line that isn't marked solved, keeping solved up to
+    /// date. refine works out a single line, and changed hears about every line that changed once
+    /// it's been written back. None on a contradiction, otherwise whether anything changed
+    pub fn sweep(
+        &self,
+        cells: &mut [Square],
+        solved: &mut [bool],
+        mut refine: impl FnMut(&[Square], &H) -> Refined,
+        mut changed: impl FnMut(usize, &[Square]),
+    ) -> Option<bool> {
+        let mut any_changed = false;
+        for (i, solved) in solved.iter_mut().enumerate() {
+            if *solved {
+                continue;
+            }
+            let (new_line, line_solved, line_changed) =
+                refine(&self.read(cells, i), &self.lines[i].hint)?;
+            *solved = line_solved;
+            if line_changed {
+                any_changed = true;
+                changed(i, &new_line);
+                self.write(cells, i, new_line);
+            }
+        }
+        Some(any_changed)
+    }
+
+    /// Sweeps until everything's filled in, a sweep changes nothing, or some line can't fit its
+    /// hint
+    pub fn propagate(
+        &self,
+        cells: &mut [Square],
+        mut refine: impl FnMut(&[Square], &H) -> Refined,
+    ) -> Outcome {
+        let mut solved = vec![false; self.lines.len()];
+        loop {
+            match self.sweep(cells, &mut solved, &mut refine, |_, _| {}) {
+                None => return Outcome::Contradiction,
+                Some(true) => {}
+                // Solved lines have nothing left to change, so once a sweep changes nothing
+                // every line has been checked against how things ended up
+                Some(false) if cells.contains(&Square::Unknown) => return Outcome::Stuck,
+                Some(false) => return Outcome::Solved,
+            }
+        }
+    }
+}